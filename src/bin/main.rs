@@ -36,6 +36,9 @@ fn main() -> eyre::Result<()> {
         sprites_metadata: serde_json::from_reader(std::fs::File::open(
             images_folder.join("sprites.json"),
         )?)?,
+        tilesets: serde_json::from_reader(std::fs::File::open(
+            images_folder.join("tilesets.json"),
+        )?)?,
         aesthetic_shader_src: std::fs::read_to_string(shaders_folder.join("aesthetic.glsl"))?,
         menu_shader_src: std::fs::read_to_string(shaders_folder.join("menu.glsl"))?,
         vignette_shader_src: std::fs::read_to_string(shaders_folder.join("vignette.glsl"))?,
@@ -59,6 +62,10 @@ fn main() -> eyre::Result<()> {
     };
 
     let mut game = Game::new(ctx, now(), width as _, height as _, resources)?;
+    // replays a run shared from someone else's HUD, e.g. `haex 4B8ZT-K1Q3M`
+    if let Some(code) = std::env::args().nth(1) {
+        game.set_seed_code(&code)?;
+    }
 
     event_loop.run(move |event, _, cf| {
         use glutin::{event::*, event_loop::ControlFlow};
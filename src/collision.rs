@@ -0,0 +1,121 @@
+use crate::map::Coord;
+
+/// The cells an actor has a claim on. Contact is worked out from these rather than from
+/// where things are drawn, so being a pixel away on the other side of a wall is a miss.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Occupancy {
+    /// Standing still in a cell.
+    At(Coord),
+    /// Partway from one cell to the next, `progress` going from 0 to 1. Both cells stay
+    /// reserved until it gets there.
+    Moving {
+        from: Coord,
+        to: Coord,
+        progress: f32,
+    },
+}
+
+impl Occupancy {
+    /// The cell it's mostly in, which changes over halfway through a move.
+    pub fn cell(&self) -> Coord {
+        match *self {
+            Occupancy::At(at) => at,
+            Occupancy::Moving { from, to, progress } => {
+                if progress < 0.5 {
+                    from
+                } else {
+                    to
+                }
+            }
+        }
+    }
+
+    pub fn reserves(&self, coord: Coord) -> bool {
+        match *self {
+            Occupancy::At(at) => at == coord,
+            Occupancy::Moving { from, to, .. } => from == coord || to == coord,
+        }
+    }
+
+    /// Whether the two have run into each other. Moving into a cell someone's standing in
+    /// is a hit as soon as the move starts, as is heading for the same cell as someone
+    /// else. Two moves that swap cells pass through each other somewhere in the gap so
+    /// they're a hit whatever the progress, otherwise following right behind someone is
+    /// only a hit once the one behind is further into the cell than the one leaving it.
+    pub fn collides(&self, other: &Occupancy) -> bool {
+        match (*self, *other) {
+            (Occupancy::At(a), Occupancy::At(b)) => a == b,
+            (Occupancy::At(at), moving) | (moving, Occupancy::At(at)) => moving.reserves(at),
+            (
+                Occupancy::Moving {
+                    from: a_from,
+                    to: a_to,
+                    ..
+                },
+                Occupancy::Moving {
+                    from: b_from,
+                    to: b_to,
+                    ..
+                },
+            ) => {
+                let swapping = a_from == b_to && a_to == b_from;
+                a_to == b_to || swapping || self.cell() == other.cell()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moving(from: Coord, to: Coord, progress: f32) -> Occupancy {
+        Occupancy::Moving { from, to, progress }
+    }
+
+    #[test]
+    fn standing_test() {
+        assert!(Occupancy::At((1, 1)).collides(&Occupancy::At((1, 1))));
+        // right next to each other is fine, wall or no wall
+        assert!(!Occupancy::At((1, 1)).collides(&Occupancy::At((2, 1))));
+        assert!(!Occupancy::At((1, 1)).collides(&Occupancy::At((2, 2))));
+    }
+
+    #[test]
+    fn reservation_test() {
+        let mover = moving((0, 0), (1, 0), 0.1);
+        assert_eq!(mover.cell(), (0, 0));
+        assert_eq!(moving((0, 0), (1, 0), 0.5).cell(), (1, 0));
+        assert!(mover.reserves((1, 0)));
+        assert!(!mover.reserves((2, 0)));
+
+        // into a standing actor's cell is a hit straight away, from either side
+        assert!(mover.collides(&Occupancy::At((1, 0))));
+        assert!(Occupancy::At((1, 0)).collides(&mover));
+        assert!(!mover.collides(&Occupancy::At((0, 1))));
+
+        // both heading for the same cell
+        assert!(mover.collides(&moving((1, 1), (1, 0), 0.1)));
+    }
+
+    #[test]
+    fn swept_test() {
+        // swapping cells is a hit however far along either of them is
+        for (a, b) in [(0.1, 0.1), (0.1, 0.9), (0.9, 0.1), (0.9, 0.9)] {
+            let a = moving((0, 0), (1, 0), a);
+            let b = moving((1, 0), (0, 0), b);
+            assert!(a.collides(&b));
+            assert!(b.collides(&a));
+        }
+
+        // going the same way one behind the other
+        let ahead = moving((1, 0), (2, 0), 0.6);
+        assert!(!moving((0, 0), (1, 0), 0.4).collides(&ahead));
+        assert!(!moving((0, 0), (1, 0), 0.9).collides(&ahead));
+        let ahead = moving((1, 0), (2, 0), 0.2);
+        assert!(moving((0, 0), (1, 0), 0.6).collides(&ahead));
+
+        // passing by in neighbouring rows
+        assert!(!moving((0, 0), (1, 0), 0.5).collides(&moving((1, 1), (0, 1), 0.5)));
+    }
+}
@@ -1,3 +1,7 @@
+mod behaviors;
+
+pub use behaviors::{toward, Chase, EnemyBehavior, Hunt, Patrol, Senses, Sentry, Wander};
+
 use crate::programs::{State, StateMut};
 use solstice_2d::Draw;
 
@@ -31,118 +35,224 @@ impl Timer {
 }
 
 #[derive(Debug)]
-enum BasicEnemyState {
+enum Movement {
     Stationary(Timer),
     Moving([f32; 2], Timer),
 }
 
-#[derive(Debug)]
-struct BasicEnemy {
-    state: BasicEnemyState,
-    prev_position: [f32; 2],
+/// What an enemy looks like and how quickly it gets about. Where it goes is up to its
+/// `EnemyBehavior`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum EnemyType {
+    Basic,
+    /// Scuttles about three times as quick as the others.
+    Crawler,
+    /// Slow, but walls don't stop it.
+    Hunter,
 }
 
-impl BasicEnemy {
-    pub const WAIT_TIME: std::time::Duration = std::time::Duration::from_secs(1);
+impl EnemyType {
+    fn wait_time(self) -> std::time::Duration {
+        match self {
+            EnemyType::Basic => std::time::Duration::from_secs(1),
+            EnemyType::Crawler => std::time::Duration::from_secs_f32(0.3),
+            EnemyType::Hunter => std::time::Duration::from_secs_f32(1.6),
+        }
+    }
+
+    /// Where stepping out of `from` takes it, if it can go that way.
+    fn step(
+        self,
+        map: &crate::state::Map,
+        from: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> Option<crate::map::Coord> {
+        match self {
+            // through walls and locked doors alike, just not out into the void
+            EnemyType::Hunter => map
+                .grid
+                .neighbor(from, direction)
+                .filter(|next| !map.grid.is_void(*next)),
+            EnemyType::Basic | EnemyType::Crawler => {
+                map.grid.valid_move_with(from, direction, &map.locks)
+            }
+        }
+    }
 }
 
-#[derive(Debug)]
-enum EnemyType {
-    Basic(BasicEnemy),
+/// Every kind of enemy a level can ask for, see `crate::map::EnemyGenSettings`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EnemyKind {
+    Basic,
+    Chaser,
+    Patroller,
+    Sentry,
+    Crawler,
+    Hunter,
+}
+
+impl EnemyKind {
+    /// An enemy of this kind starting out at `spawn`. `rng` sets it up and `seed` is what
+    /// it makes its own decisions with from then on.
+    pub fn spawn<R: rand::Rng>(
+        self,
+        map: &crate::state::NavigableMap,
+        spawn: crate::map::Coord,
+        seed: u64,
+        rng: &mut R,
+    ) -> Enemy {
+        let (ty, behavior): (EnemyType, Box<dyn EnemyBehavior>) = match self {
+            EnemyKind::Basic => (EnemyType::Basic, Box::new(Wander)),
+            EnemyKind::Chaser => (EnemyType::Basic, Box::new(Chase::new(Chase::SIGHT))),
+            EnemyKind::Patroller => (
+                EnemyType::Basic,
+                Box::new(Patrol::new(&map.graph.inner, spawn, rng)),
+            ),
+            EnemyKind::Sentry => (
+                EnemyType::Basic,
+                Box::new(Sentry::new(map.inner.drawn(), spawn)),
+            ),
+            // quick but short sighted
+            EnemyKind::Crawler => (EnemyType::Crawler, Box::new(Chase::new(2))),
+            EnemyKind::Hunter => (EnemyType::Hunter, Box::new(Hunt)),
+        };
+        let (x, y) = map.inner.coord_to_mid_pixel(spawn);
+        Enemy::new(x, y, ty, behavior, seed)
+    }
+}
+
+impl crate::map::EnemyGenSettings {
+    /// Every enemy to put on the map, `*_count` of each kind.
+    pub fn kinds(&self) -> Vec<EnemyKind> {
+        let counts = [
+            (EnemyKind::Basic, self.basic_count),
+            (EnemyKind::Chaser, self.chaser_count),
+            (EnemyKind::Patroller, self.patroller_count),
+            (EnemyKind::Sentry, self.sentry_count),
+            (EnemyKind::Crawler, self.crawler_count),
+            (EnemyKind::Hunter, self.hunter_count),
+        ];
+        IntoIterator::into_iter(counts)
+            .flat_map(|(kind, count)| std::iter::repeat_n(kind, count))
+            .collect()
+    }
 }
 
 #[derive(Debug)]
 pub struct Enemy {
     pub position: [f32; 2],
+    prev_position: [f32; 2],
+    movement: Movement,
     ty: EnemyType,
-    t: std::time::Duration,
+    behavior: Box<dyn EnemyBehavior>,
+    /// Seeded from the level and the order the enemy was spawned in, see `crate::seed::enemy`,
+    /// so that the same seed and the same moves from the player play out the same way.
+    rng: rand::rngs::SmallRng,
+    /// Where a scanner last saw the player, along with how much longer it's worth chasing.
+    alerted: Option<(crate::map::Coord, std::time::Duration)>,
 }
 
 impl Enemy {
-    pub fn new_basic(x: f32, y: f32) -> Self {
+    pub const ALERT_TIME: std::time::Duration = std::time::Duration::from_secs(6);
+
+    fn new(x: f32, y: f32, ty: EnemyType, behavior: Box<dyn EnemyBehavior>, seed: u64) -> Self {
         Self {
             position: [x, y],
-            ty: EnemyType::Basic(BasicEnemy {
-                state: BasicEnemyState::Stationary(Timer::new(BasicEnemy::WAIT_TIME)),
-                prev_position: [x, y],
-            }),
-            t: Default::default(),
+            prev_position: [x, y],
+            movement: Movement::Stationary(Timer::new(ty.wait_time())),
+            ty,
+            behavior,
+            rng: rand::SeedableRng::seed_from_u64(seed),
+            alerted: None,
         }
     }
 
+    pub fn new_basic(x: f32, y: f32, seed: u64) -> Self {
+        Self::new(x, y, EnemyType::Basic, Box::new(Wander), seed)
+    }
+
+    /// Sends the enemy after `to` instead of doing whatever it was doing for a while.
+    pub fn alert(&mut self, to: crate::map::Coord) {
+        self.alerted = Some((to, Self::ALERT_TIME));
+    }
+
     pub fn update(&mut self, dt: std::time::Duration, ctx: &StateMut) {
-        self.t += dt;
-        let mut rng: rand::rngs::SmallRng =
-            rand::SeedableRng::seed_from_u64(self.t.as_millis() as _);
-        let mut directions = crate::map::Direction::cardinals();
-        rand::seq::SliceRandom::shuffle(&mut directions[..], &mut rng);
-        match &mut self.ty {
-            EnemyType::Basic(inner) => match &mut inner.state {
-                BasicEnemyState::Stationary(timer) => {
-                    if timer.update(dt) {
-                        let [x, y] = self.position;
-                        let coord = ctx.map.pixel_to_coord((x, y));
-                        let next = std::array::IntoIter::new(directions)
-                            .find_map(|dir| ctx.map.grid.valid_move(coord, dir));
-                        if let Some(next) = next {
-                            inner.prev_position = self.position;
-                            let (x, y) = ctx.map.coord_to_mid_pixel(next);
-                            inner.state =
-                                BasicEnemyState::Moving([x, y], Timer::new(BasicEnemy::WAIT_TIME));
-                        }
+        self.alerted = self
+            .alerted
+            .and_then(|(to, left)| Some((to, left.checked_sub(dt)?)));
+        let wait_time = self.ty.wait_time();
+        match &mut self.movement {
+            Movement::Stationary(timer) => {
+                if timer.update(dt) {
+                    let [x, y] = self.position;
+                    let coord = ctx.map.pixel_to_coord((x, y));
+                    let senses =
+                        Senses::new(ctx.map, ctx.map.pixel_to_coord(ctx.player.position()));
+                    let direction = match self.alerted {
+                        Some((to, _)) => toward(&senses, coord, to),
+                        None => self.behavior.next_move(coord, &senses, &mut self.rng),
+                    };
+                    let ty = self.ty;
+                    let next = direction.and_then(|direction| ty.step(ctx.map, coord, direction));
+                    if let Some(next) = next {
+                        self.prev_position = self.position;
+                        let (x, y) = ctx.map.coord_to_mid_pixel(next);
+                        self.movement = Movement::Moving([x, y], Timer::new(wait_time));
                     }
                 }
-                BasicEnemyState::Moving(target, timer) => {
-                    let ratio = timer.ratio();
-                    for (a, b) in self.position.iter_mut().zip(target.iter()) {
-                        *a = crate::lerp(*a, *b, ratio);
-                    }
-                    if timer.update(dt) {
-                        inner.state = BasicEnemyState::Stationary(Timer::new(BasicEnemy::WAIT_TIME))
-                    }
+            }
+            Movement::Moving(target, timer) => {
+                let ratio = timer.ratio();
+                for (a, b) in self.position.iter_mut().zip(target.iter()) {
+                    *a = crate::lerp(*a, *b, ratio);
+                }
+                if timer.update(dt) {
+                    self.movement = Movement::Stationary(Timer::new(wait_time))
                 }
-            },
+            }
+        }
+    }
+
+    /// The cells the enemy has a claim on, see `crate::collision::Occupancy`.
+    pub fn occupancy(&self, map: &crate::state::Map) -> crate::collision::Occupancy {
+        let [x, y] = self.position;
+        match &self.movement {
+            Movement::Stationary(_) => crate::collision::Occupancy::At(map.pixel_to_coord((x, y))),
+            Movement::Moving([tx, ty], timer) => {
+                let [px, py] = self.prev_position;
+                crate::collision::Occupancy::Moving {
+                    from: map.pixel_to_coord((px, py)),
+                    to: map.pixel_to_coord((*tx, *ty)),
+                    progress: timer.ratio(),
+                }
+            }
         }
     }
 
     pub fn collides_with(&self, player: &crate::player::Player, map: &crate::state::Map) -> bool {
-        let [tw, th] = map.tile_size;
-        let (px, py) = player.position();
-        let [sx, sy] = self.position;
-        (px - sx).abs() < tw && (py - sy).abs() < th
+        self.occupancy(map).collides(&player.occupancy(map))
     }
 
     pub fn render(&self, ctx: &mut State) {
-        match &self.ty {
-            EnemyType::Basic(inner) => {
-                let [tw, th] = ctx.map.tile_size;
-                let [tw, th] = [tw * 0.8, th * 0.8];
-                let [x1, y1] = self.position;
-                let [x2, y2] = inner.prev_position;
-                let angle = (y2 - y1).atan2(x2 - x1) + std::f32::consts::FRAC_PI_2;
-                let transform = solstice_2d::Transform2D::translation(x1, y1)
-                    * solstice_2d::Transform2D::rotation(solstice_2d::Rad(angle));
-                ctx.ctx.g.image_with_transform(
-                    ctx.ctx
-                        .resources
-                        .sprites_metadata
-                        .enemy1_body
-                        .with_size(tw, th)
-                        .center_on(0., 0.),
-                    &ctx.ctx.resources.sprites,
-                    transform,
-                );
-                ctx.ctx.g.image_with_transform(
-                    ctx.ctx
-                        .resources
-                        .sprites_metadata
-                        .enemy1_color
-                        .with_size(tw, th)
-                        .center_on(0., 0.),
-                    &ctx.ctx.resources.sprites,
-                    transform,
-                );
-            }
+        let sprites = &ctx.ctx.resources.sprites_metadata;
+        let (body, color, scale) = match self.ty {
+            EnemyType::Basic => (sprites.enemy1_body, sprites.enemy1_color, 0.8),
+            EnemyType::Crawler => (sprites.enemy2_body, sprites.enemy2_color, 0.6),
+            EnemyType::Hunter => (sprites.enemy3_body, sprites.enemy3_color, 0.9),
+        };
+        let [tw, th] = ctx.map.tile_size;
+        let [tw, th] = [tw * scale, th * scale];
+        let [x1, y1] = self.position;
+        let [x2, y2] = self.prev_position;
+        let angle = (y2 - y1).atan2(x2 - x1) + std::f32::consts::FRAC_PI_2;
+        let transform = solstice_2d::Transform2D::translation(x1, y1)
+            * solstice_2d::Transform2D::rotation(solstice_2d::Rad(angle));
+        for sprite in IntoIterator::into_iter([body, color]) {
+            ctx.ctx.g.image_with_transform(
+                sprite.with_size(tw, th).center_on(0., 0.),
+                &ctx.ctx.resources.sprites,
+                transform,
+            );
         }
     }
 }
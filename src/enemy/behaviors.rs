@@ -0,0 +1,364 @@
+use crate::map::{Coord, Direction, DirectionGrid, Graph, Locks};
+use crate::state::Map;
+use rand::seq::SliceRandom;
+
+/// What an enemy can tell about the world when it's deciding where to go.
+pub struct Senses<'a> {
+    /// The walls as they really are, for getting about.
+    pub grid: &'a DirectionGrid,
+    /// The walls as they're drawn, for looking about, see `Map::visible_from`.
+    pub drawn: &'a DirectionGrid,
+    pub locks: &'a Locks,
+    /// The cell the player is in.
+    pub player: Coord,
+}
+
+impl<'a> Senses<'a> {
+    pub fn new(map: &'a Map, player: Coord) -> Self {
+        Self {
+            grid: &map.grid,
+            drawn: map.drawn(),
+            locks: &map.locks,
+            player,
+        }
+    }
+
+    fn sees_player(&self, from: Coord, sight: usize) -> bool {
+        let visible = crate::map::visible(self.drawn, from, sight);
+        visible
+            .checked_coord_to_index(self.player)
+            .is_some_and(|index| visible.data[index])
+    }
+}
+
+/// Decides where an enemy steps next each time it's ready to move.
+pub trait EnemyBehavior: std::fmt::Debug {
+    /// The way to step out of `from`, or nothing to stay put. Walls and doors are checked
+    /// afterwards so a step into one is just a wasted turn.
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction>;
+}
+
+/// The step out of `from` that gets closest to `to`, if any of them get closer at all.
+pub fn toward(senses: &Senses, from: Coord, to: Coord) -> Option<Direction> {
+    let distances = senses.grid.distances(&[to]);
+    let here = distances.data[distances.checked_coord_to_index(from)?];
+    IntoIterator::into_iter(Direction::cardinals())
+        .filter_map(|direction| {
+            let next = senses.grid.valid_move_with(from, direction, senses.locks)?;
+            Some((distances.data[distances.coord_to_index(next)], direction))
+        })
+        .filter(|(distance, _)| *distance < here)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, direction)| direction)
+}
+
+fn wander(from: Coord, senses: &Senses, rng: &mut rand::rngs::SmallRng) -> Option<Direction> {
+    let mut directions = Direction::cardinals();
+    directions.shuffle(rng);
+    IntoIterator::into_iter(directions).find(|direction| {
+        senses
+            .grid
+            .valid_move_with(from, *direction, senses.locks)
+            .is_some()
+    })
+}
+
+/// Picks a way out at random every time.
+#[derive(Debug)]
+pub struct Wander;
+
+impl EnemyBehavior for Wander {
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction> {
+        wander(from, senses, rng)
+    }
+}
+
+/// Wanders until it sees the player, then heads for wherever it last saw them.
+#[derive(Debug)]
+pub struct Chase {
+    sight: usize,
+    last_seen: Option<Coord>,
+}
+
+impl Chase {
+    pub const SIGHT: usize = 4;
+
+    pub fn new(sight: usize) -> Self {
+        Self {
+            sight,
+            last_seen: None,
+        }
+    }
+}
+
+impl EnemyBehavior for Chase {
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction> {
+        if senses.sees_player(from, self.sight) {
+            self.last_seen = Some(senses.player);
+        }
+        let chasing = self
+            .last_seen
+            .and_then(|last_seen| toward(senses, from, last_seen));
+        if chasing.is_none() {
+            // lost them
+            self.last_seen = None;
+        }
+        chasing.or_else(|| wander(from, senses, rng))
+    }
+}
+
+/// Walks the same route there and back again forever.
+#[derive(Debug)]
+pub struct Patrol {
+    route: Vec<Coord>,
+    next: usize,
+}
+
+impl Patrol {
+    pub const REACH: usize = 6;
+
+    /// A route from `from` out to one of the furthest cells up to `REACH` steps away.
+    pub fn new<R: rand::Rng>(graph: &Graph, from: Coord, rng: &mut R) -> Self {
+        let search = crate::map::bfs(graph, from);
+        let mut ends = search
+            .iter()
+            .filter(|(_, (distance, _))| *distance <= Self::REACH)
+            .map(|(node, (distance, _))| (*distance, *node))
+            .collect::<Vec<_>>();
+        ends.sort_unstable();
+        let furthest = ends.last().map_or(0, |(distance, _)| *distance);
+        ends.retain(|(distance, _)| *distance == furthest);
+        let end = ends.choose(rng).map_or(from, |(_, node)| *node);
+
+        let mut route = crate::map::bfs_path(&search, end);
+        let back = route.len().saturating_sub(2);
+        route.extend(route.clone().into_iter().rev().skip(1).take(back));
+        if route.is_empty() {
+            route.push(from);
+        }
+        Self { route, next: 0 }
+    }
+}
+
+impl EnemyBehavior for Patrol {
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        _rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction> {
+        if self.route[self.next] == from {
+            self.next = (self.next + 1) % self.route.len();
+        }
+        // anything that knocked it off of the route just means a detour back to it
+        toward(senses, from, self.route[self.next])
+    }
+}
+
+/// Stands at its post watching a corridor and goes after the player while they're in it.
+#[derive(Debug)]
+pub struct Sentry {
+    post: Coord,
+    facing: Direction,
+}
+
+impl Sentry {
+    pub const RANGE: usize = 6;
+
+    /// Faces down the longest straight run of open cells out of `post`.
+    pub fn new(grid: &DirectionGrid, post: Coord) -> Self {
+        let facing = IntoIterator::into_iter(Direction::cardinals())
+            .max_by_key(|direction| Self::corridor(grid, post, *direction).len())
+            .unwrap_or(Direction::N);
+        Self { post, facing }
+    }
+
+    fn corridor(grid: &DirectionGrid, post: Coord, facing: Direction) -> Vec<Coord> {
+        std::iter::successors(Some(post), |cell| grid.valid_move(*cell, facing))
+            .skip(1)
+            .take(Self::RANGE)
+            .collect()
+    }
+
+    pub fn watches(&self, grid: &DirectionGrid, coord: Coord) -> bool {
+        coord == self.post || Self::corridor(grid, self.post, self.facing).contains(&coord)
+    }
+}
+
+impl EnemyBehavior for Sentry {
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        _rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction> {
+        if self.watches(senses.drawn, senses.player) {
+            toward(senses, from, senses.player)
+        } else {
+            toward(senses, from, self.post)
+        }
+    }
+}
+
+/// Smells the player out through the walls once they're close enough and heads straight
+/// for them, walls or not. Only makes sense for an enemy that can go through walls.
+#[derive(Debug)]
+pub struct Hunt;
+
+impl Hunt {
+    pub const RANGE: usize = 5;
+
+    /// The step from `from` that closes whichever gap to `to` is bigger, walls or not.
+    fn straight_at(from: Coord, to: Coord) -> Option<Direction> {
+        let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+        let distance = (dx.abs() + dy.abs()) as usize;
+        if distance == 0 || distance > Self::RANGE {
+            return None;
+        }
+        Some(match (dx.abs() >= dy.abs(), dx > 0, dy > 0) {
+            (true, true, _) => Direction::E,
+            (true, false, _) => Direction::W,
+            (false, _, true) => Direction::S,
+            (false, _, false) => Direction::N,
+        })
+    }
+}
+
+impl EnemyBehavior for Hunt {
+    fn next_move(
+        &mut self,
+        from: Coord,
+        senses: &Senses,
+        rng: &mut rand::rngs::SmallRng,
+    ) -> Option<Direction> {
+        Self::straight_at(from, senses.player).or_else(|| {
+            let mut directions = Direction::cardinals();
+            directions.shuffle(rng);
+            let grid = senses.grid;
+            IntoIterator::into_iter(directions).find(|direction| {
+                grid.neighbor(from, *direction)
+                    .is_some_and(|next| !grid.is_void(next))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::fixtures::MAZE;
+    use rand::SeedableRng;
+
+    #[test]
+    fn chase_test() {
+        let (grid, _) = crate::map::parse_ascii(MAZE).unwrap();
+        let locks = Locks::default();
+        let senses = |player| Senses {
+            grid: &grid,
+            drawn: &grid,
+            locks: &locks,
+            player,
+        };
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut chase = Chase::new(2);
+
+        // straight down the corridor
+        let next = chase.next_move((1, 0), &senses((3, 0)), &mut rng);
+        assert_eq!(next, Some(Direction::E));
+        assert_eq!(chase.last_seen, Some((3, 0)));
+
+        // the player ducked around the corner but it still goes where it saw them
+        let next = chase.next_move((2, 0), &senses((2, 1)), &mut rng);
+        assert_eq!(next, Some(Direction::E));
+        assert_eq!(chase.last_seen, Some((3, 0)));
+
+        // and once it's there with them out of sight it gives up
+        let next = chase.next_move((3, 0), &senses((0, 1)), &mut rng);
+        assert_eq!(chase.last_seen, None);
+        assert!(next.is_some_and(|direction| grid.valid_move((3, 0), direction).is_some()));
+    }
+
+    #[test]
+    fn patrol_test() {
+        let (grid, _) = crate::map::parse_ascii(MAZE).unwrap();
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let patrol = Patrol::new(&grid.as_graph(), (0, 0), &mut rng);
+        // out to the far end and back, ready to go round again
+        assert_eq!(
+            patrol.route,
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (3, 1),
+                (2, 1),
+                (3, 1),
+                (3, 0),
+                (2, 0),
+                (1, 0)
+            ]
+        );
+
+        let patrol = Patrol::new(&grid.as_graph(), (9, 9), &mut rng);
+        assert_eq!(patrol.route, vec![(9, 9)]);
+    }
+
+    #[test]
+    fn sentry_test() {
+        let (grid, _) = crate::map::parse_ascii(MAZE).unwrap();
+        let mut sentry = Sentry::new(&grid, (0, 0));
+        assert_eq!(sentry.facing, Direction::E);
+        assert!(sentry.watches(&grid, (3, 0)));
+        assert!(!sentry.watches(&grid, (3, 1)));
+        assert!(!sentry.watches(&grid, (0, 1)));
+
+        // it can't see through a secret passage that's still shut
+        let mut drawn = grid.clone();
+        let index = drawn.coord_to_index((1, 0));
+        drawn.data[index].remove(Direction::E);
+        let index = drawn.coord_to_index((2, 0));
+        drawn.data[index].remove(Direction::W);
+        let locks = Locks::default();
+        let mut senses = Senses {
+            grid: &grid,
+            drawn: &grid,
+            locks: &locks,
+            player: (3, 0),
+        };
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        assert_eq!(
+            sentry.next_move((1, 0), &senses, &mut rng),
+            Some(Direction::E)
+        );
+        senses.drawn = &drawn;
+        assert_eq!(
+            sentry.next_move((1, 0), &senses, &mut rng),
+            Some(Direction::W)
+        );
+    }
+
+    #[test]
+    fn hunt_test() {
+        assert_eq!(Hunt::straight_at((0, 0), (0, 1)), Some(Direction::S));
+        assert_eq!(Hunt::straight_at((3, 1), (0, 0)), Some(Direction::W));
+        assert_eq!(Hunt::straight_at((3, 0), (2, 2)), Some(Direction::S));
+        assert_eq!(Hunt::straight_at((3, 0), (3, 0)), None);
+        assert_eq!(Hunt::straight_at((0, 0), (9, 9)), None);
+    }
+}
@@ -1,10 +1,12 @@
 pub mod audio;
+mod collision;
 mod cron;
 mod enemy;
 mod map;
 mod player;
 mod programs;
 pub mod resources;
+mod seed;
 mod state;
 #[cfg(target_arch = "wasm32")]
 pub mod web;
@@ -39,16 +41,78 @@ impl InputState {
     }
 }
 
-#[derive(Clone)]
+const TUTORIAL_LEVEL: &str = include_str!("../docs/levels/tutorial.txt");
+
+const OCTAGON_MASK: &str = "
+...######...
+..########..
+.##########.
+############
+############
+#####..#####
+#####..#####
+############
+############
+.##########.
+..########..
+...######...
+";
+
+#[derive(Debug, Clone)]
 pub enum ProgressionType {
     Standard(Box<MapProgression>),
+    /// A hand-made level. `progression` still picks the aesthetic and where the level leads.
+    Authored {
+        level: Box<map::Level>,
+        progression: Box<MapProgression>,
+    },
     BadEnding,
 }
 
-#[derive(Clone)]
+impl ProgressionType {
+    fn progression(&self) -> Option<&MapProgression> {
+        match self {
+            ProgressionType::Standard(progression) => Some(progression),
+            ProgressionType::Authored { progression, .. } => Some(progression),
+            ProgressionType::BadEnding => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct MapProgression {
     settings: map::MapGenSettings,
-    exit: Option<ProgressionType>,
+    /// Every way out of the level. A level without any is a dead end.
+    exits: Vec<Exit>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExitKind {
+    /// The first one goes at the end of the route through the level, any others out
+    /// in far off dead ends.
+    Route,
+    /// Walled off in a pocket of the maze that only `NoClip` gets into.
+    Hidden,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Exit {
+    pub kind: ExitKind,
+    /// Which of `Progression::levels` the exit leads to.
+    pub to: usize,
+}
+
+/// Every level in the game. Exits point at levels by index so that branches can join back
+/// up with each other, the run starts at the first one.
+#[derive(Debug, Clone)]
+pub struct Progression {
+    pub levels: Vec<ProgressionType>,
+}
+
+impl Progression {
+    pub fn start(&self) -> &ProgressionType {
+        self.levels.first().unwrap_or(&ProgressionType::BadEnding)
+    }
 }
 
 pub struct AudioSinks {
@@ -67,7 +131,9 @@ struct Static {
     canvas: solstice_2d::Canvas,
     input_state: InputState,
     audio_ctx: audio::AudioContext,
-    maps: MapProgression,
+    maps: Progression,
+    /// Set to replay a shared run instead of starting a new one, see `Game::set_seed_code`.
+    seed: Option<seed::RunSeed>,
     time: std::time::Duration,
     sinks: Option<AudioSinks>,
 }
@@ -86,6 +152,7 @@ impl Static {
             audio_ctx: &mut self.audio_ctx,
             cron,
             maps: &self.maps,
+            seed: self.seed,
             time: self.time,
             audio_sinks: &mut self.sinks,
         }
@@ -147,60 +214,261 @@ impl Game {
             },
         )?;
 
-        let maps = MapProgression {
+        // where each level sits in `Progression::levels`
+        const TUTORIAL: usize = 0;
+        const SMALL: usize = 1;
+        const MEDIUM: usize = 2;
+        const OCTAGON: usize = 3;
+        const BAD_ENDING: usize = 4;
+        const ESCAPE_HATCH: usize = 5;
+        let route = |to| Exit {
+            kind: ExitKind::Route,
+            to,
+        };
+
+        let small = MapProgression {
             settings: map::MapGenSettings {
                 width: 4,
                 height: 4,
+                mask: None,
+                seed: None,
+                floors: 1,
+                generator: map::Generator::GrowingTree(map::Weights::corridors()),
+                braid: map::BraidSettings::default(),
+                rooms: map::RoomSettings::default(),
                 programs: map::ProgramGenSettings::default(),
                 enemies: map::EnemyGenSettings::default(),
+                difficulty: map::DifficultyBand::default(),
+                sight: 3,
+                doors: 0,
+                secrets: 0,
+                shift_every: None,
+                hazards: Default::default(),
+                tileset: map::DEFAULT_TILESET,
                 aesthetic: crate::AestheticShader {
                     random_shift_scale: 0.001,
                     radial_scale: 1.0,
                     ..Default::default()
                 },
             },
-            exit: Some(ProgressionType::Standard(Box::new(MapProgression {
+            exits: vec![route(MEDIUM)],
+        };
+
+        let medium = MapProgression {
+            settings: map::MapGenSettings {
+                width: 8,
+                height: 8,
+                mask: None,
+                seed: None,
+                floors: 1,
+                generator: map::Generator::RecursiveBacktracker,
+                braid: map::BraidSettings {
+                    dead_end_ratio: 0.25,
+                    extra_loops: 0,
+                },
+                rooms: map::RoomSettings::default(),
+                programs: map::ProgramGenSettings {
+                    nop_slide_count: 1,
+                    noclip_count: 1,
+                },
+                enemies: map::EnemyGenSettings {
+                    basic_count: 1,
+                    patroller_count: 1,
+                    ..Default::default()
+                },
+                // a long way round rather than a short hop to the exit
+                difficulty: map::DifficultyBand {
+                    solution_ratio: map::Bounds::new(0.45, 1.),
+                    attempts: 8,
+                    ..Default::default()
+                },
+                sight: 2,
+                doors: 1,
+                secrets: 1,
+                shift_every: None,
+                hazards: map::HazardSettings {
+                    firewalls: 0,
+                    scanners: 1,
+                    slow: 2,
+                },
+                tileset: map::DEFAULT_TILESET,
+                aesthetic: AestheticShader {
+                    block_threshold: 0.093,
+                    line_threshold: 0.33,
+                    random_shift_scale: 0.001,
+                    radial_scale: 1.0,
+                    ..Default::default()
+                },
+            },
+            // clipping into the walled off exit breaks out of the main sequence
+            exits: vec![
+                route(OCTAGON),
+                Exit {
+                    kind: ExitKind::Hidden,
+                    to: ESCAPE_HATCH,
+                },
+            ],
+        };
+
+        let octagon = MapProgression {
+            settings: map::MapGenSettings {
+                width: 12,
+                height: 12,
+                mask: Some(map::Mask::from_ascii(OCTAGON_MASK)?),
+                seed: None,
+                floors: 2,
+                generator: map::Generator::Wilson,
+                braid: map::BraidSettings {
+                    dead_end_ratio: 0.5,
+                    extra_loops: 2,
+                },
+                rooms: map::RoomSettings {
+                    count: 2,
+                    min_size: 2,
+                    max_size: 3,
+                    enemy_rooms: 1,
+                    pickup_rooms: 0,
+                    exit_room: true,
+                },
+                programs: map::ProgramGenSettings {
+                    nop_slide_count: 1,
+                    noclip_count: 2,
+                },
+                enemies: map::EnemyGenSettings {
+                    basic_count: 1,
+                    chaser_count: 1,
+                    patroller_count: 1,
+                    sentry_count: 1,
+                    hunter_count: 1,
+                    ..Default::default()
+                },
+                difficulty: map::DifficultyBand {
+                    solution_ratio: map::Bounds::new(0.2, 1.),
+                    river: map::Bounds::new(1.5, f32::INFINITY),
+                    attempts: 8,
+                    ..Default::default()
+                },
+                sight: 2,
+                doors: 1,
+                secrets: 2,
+                shift_every: None,
+                hazards: map::HazardSettings {
+                    firewalls: 2,
+                    scanners: 2,
+                    slow: 3,
+                },
+                // without filled in corners the rooms read as halls full of pillars
+                tileset: "pillars",
+                aesthetic: AestheticShader {
+                    block_threshold: 0.11,
+                    line_threshold: 0.39,
+                    random_shift_scale: 0.001,
+                    radial_scale: 1.0,
+                    ..Default::default()
+                },
+            },
+            exits: vec![route(BAD_ENDING)],
+        };
+
+        // the rules stop holding here: no dead ends, walls that won't stay put and
+        // a way back into the main sequence a level further on
+        let escape_hatch = MapProgression {
+            settings: map::MapGenSettings {
+                width: 10,
+                height: 10,
+                mask: None,
+                seed: None,
+                floors: 1,
+                generator: map::Generator::Wilson,
+                braid: map::BraidSettings {
+                    dead_end_ratio: 1.,
+                    extra_loops: 12,
+                },
+                rooms: map::RoomSettings::default(),
+                programs: map::ProgramGenSettings {
+                    nop_slide_count: 2,
+                    noclip_count: 0,
+                },
+                enemies: map::EnemyGenSettings {
+                    chaser_count: 3,
+                    crawler_count: 2,
+                    hunter_count: 1,
+                    ..Default::default()
+                },
+                difficulty: map::DifficultyBand::default(),
+                sight: 8,
+                doors: 0,
+                secrets: 0,
+                shift_every: Some(std::time::Duration::from_secs(3)),
+                hazards: Default::default(),
+                tileset: "pillars",
+                aesthetic: AestheticShader {
+                    block_threshold: 0.15,
+                    line_threshold: 0.5,
+                    random_shift_scale: 0.004,
+                    radial_scale: 1.0,
+                    ..Default::default()
+                },
+            },
+            exits: vec![route(OCTAGON)],
+        };
+
+        let tutorial = map::Level::from_ascii(TUTORIAL_LEVEL)?;
+        let tutorial = ProgressionType::Authored {
+            progression: Box::new(MapProgression {
                 settings: map::MapGenSettings {
-                    width: 8,
-                    height: 8,
+                    width: tutorial.width,
+                    height: tutorial.height,
+                    mask: None,
+                    seed: None,
+                    floors: 1,
+                    generator: map::Generator::default(),
+                    braid: map::BraidSettings::default(),
+                    rooms: map::RoomSettings::default(),
                     programs: map::ProgramGenSettings::default(),
-                    enemies: map::EnemyGenSettings { basic_count: 2 },
-                    aesthetic: AestheticShader {
-                        block_threshold: 0.093,
-                        line_threshold: 0.33,
-                        random_shift_scale: 0.001,
-                        radial_scale: 1.0,
-                        ..Default::default()
-                    },
+                    enemies: map::EnemyGenSettings::default(),
+                    difficulty: map::DifficultyBand::default(),
+                    sight: 3,
+                    doors: 0,
+                    secrets: 0,
+                    shift_every: None,
+                    hazards: Default::default(),
+                    tileset: map::DEFAULT_TILESET,
+                    aesthetic: small.settings.aesthetic,
                 },
-                exit: Some(ProgressionType::Standard(Box::new(MapProgression {
-                    settings: map::MapGenSettings {
-                        width: 12,
-                        height: 12,
-                        programs: map::ProgramGenSettings::default(),
-                        enemies: map::EnemyGenSettings { basic_count: 4 },
-                        aesthetic: AestheticShader {
-                            block_threshold: 0.11,
-                            line_threshold: 0.39,
-                            random_shift_scale: 0.001,
-                            radial_scale: 1.0,
-                            ..Default::default()
-                        },
-                    },
-                    exit: Some(ProgressionType::BadEnding),
-                }))),
-            }))),
+                exits: vec![route(SMALL)],
+            }),
+            level: Box::new(tutorial),
         };
 
-        // let maps = MapProgression {
-        //     settings: map::MapGenSettings {
-        //         width: 4,
-        //         height: 4,
-        //         programs: map::ProgramGenSettings { nop_slide_count: 0 },
-        //         aesthetic: crate::AestheticShader::default(),
-        //     },
-        //     exit: Some(ProgressionType::BadEnding),
-        // };
+        let mut levels = vec![ProgressionType::BadEnding; 6];
+        levels[TUTORIAL] = tutorial;
+        levels[SMALL] = ProgressionType::Standard(Box::new(small));
+        levels[MEDIUM] = ProgressionType::Standard(Box::new(medium));
+        levels[OCTAGON] = ProgressionType::Standard(Box::new(octagon));
+        levels[ESCAPE_HATCH] = ProgressionType::Standard(Box::new(escape_hatch));
+        let maps = Progression { levels };
+
+        // better to find a broken level now than partway through a run
+        for progression in maps.levels.iter().filter_map(ProgressionType::progression) {
+            let tileset = progression.settings.tileset;
+            if !resources.tilesets.contains_key(tileset) {
+                return Err(eyre::Report::msg(format!(
+                    "Missing the {} tileset.",
+                    tileset
+                )));
+            }
+            if let Some(exit) = progression
+                .exits
+                .iter()
+                .find(|exit| exit.to >= maps.levels.len())
+            {
+                return Err(eyre::Report::msg(format!(
+                    "An exit leads to level {} which doesn't exist.",
+                    exit.to
+                )));
+            }
+        }
 
         let audio_ctx = audio::AudioContext::new();
 
@@ -213,6 +481,7 @@ impl Game {
             input_state: Default::default(),
             audio_ctx,
             maps,
+            seed: None,
             time,
             sinks: None,
         };
@@ -225,6 +494,14 @@ impl Game {
         })
     }
 
+    /// Replays the run behind a code shown in another game's HUD. Takes effect the next
+    /// time a run is started from the menu.
+    pub fn set_seed_code(&mut self, code: &str) -> eyre::Result<()> {
+        let shared = &mut self.cron_ctx.shared;
+        shared.seed = Some(seed::RunSeed::from_code(code, &shared.maps)?);
+        Ok(())
+    }
+
     pub fn update(&mut self, time: std::time::Duration) {
         let dt = time - self.cron_ctx.shared.time;
         self.cron_ctx.shared.time = time;
@@ -1,8 +1,43 @@
+mod analysis;
+mod ascii;
+mod braid;
+mod distance;
+mod exits;
+mod floors;
+mod generators;
+mod hazards;
+mod level;
+mod locks;
+mod mask;
+mod pickups;
+mod rooms;
+mod secrets;
+mod shift;
+mod tiles;
+mod visibility;
+
+pub use analysis::{analyze, path_distances, Bounds, DifficultyBand, Metrics};
+pub use ascii::{parse_ascii, Overlay};
+pub use braid::{braid, BraidSettings};
+pub use distance::UNREACHABLE;
 use enumflags2::*;
+pub use exits::{far_dead_end, find_pocket};
+pub use floors::{layered_graph, place_ports, FloorCoord};
+pub use generators::{Generator, MazeGenerator};
+pub use hazards::{place_hazards, HazardSettings, Terrain};
+pub use level::{Level, Pickup};
+pub use locks::Locks;
+pub use mask::{apply_mask, Mask};
+pub use pickups::place_pickups;
+pub use rooms::{carve_rooms, Room, RoomSettings, RoomTag};
+pub use secrets::Secrets;
+pub use shift::{find_shift, Shift};
+pub use tiles::{Tileset, Tilesets};
+pub use visibility::visible;
 
 #[bitflags]
 #[repr(u8)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, serde::Deserialize)]
 pub enum Direction {
     N = 1 << 0,
     E = 1 << 1,
@@ -50,13 +85,23 @@ fn d1_to_d2(index: usize, width: usize) -> Coord {
     (index % width, index / width)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Grid<T> {
     pub data: Box<[T]>,
     pub width: usize,
     pub height: usize,
 }
 
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Self {
+        Self {
+            data: vec![value; width * height].into_boxed_slice(),
+            width,
+            height,
+        }
+    }
+}
+
 impl<T> Grid<T> {
     pub fn grid_size(&self) -> [usize; 2] {
         [self.width, self.height]
@@ -83,6 +128,12 @@ impl<T> Grid<T> {
         d1_to_d2(index, self.width)
     }
 
+    pub fn neighbor(&self, coord: Coord, direction: Direction) -> Option<Coord> {
+        neighbor_coord(coord, direction)
+            .ok()
+            .filter(|neighbor| self.contains(*neighbor))
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&T, Coord)> + '_ {
         let width = self.width;
         self.data
@@ -96,17 +147,7 @@ pub type DirectionGrid = Grid<BitFlags<Direction>>;
 
 impl DirectionGrid {
     pub fn new<R: rand::Rng>(width: usize, height: usize, rng: &mut R) -> Self {
-        let weights = Weights {
-            random: 1.,
-            newest: 1.,
-            ..Default::default()
-        };
-        let grid = growing_tree(width, height, weights, rng);
-        Self {
-            data: grid.into_boxed_slice(),
-            width,
-            height,
-        }
+        Generator::default().generate(width, height, rng)
     }
 
     pub fn make_open(&mut self, from: Coord, direction: Direction) {
@@ -120,6 +161,68 @@ impl DirectionGrid {
         }
     }
 
+    /// Opens a wall like `make_open` and fixes up the corner bits around it. Walls out of
+    /// the grid or into the void stay shut. Returns every cell that changed.
+    pub fn carve(&mut self, from: Coord, direction: Direction) -> Vec<Coord> {
+        let to = match self.neighbor(from, direction) {
+            Some(to) if !self.is_void(from) && !self.is_void(to) => to,
+            _ => return vec![],
+        };
+        if self.data[self.coord_to_index(from)].contains(direction) {
+            return vec![];
+        }
+        self.make_open(from, direction);
+        self.update_corners_around(&[from, to])
+    }
+
+    /// The opposite of `carve`: shuts a wall and fixes up the corner bits around it.
+    /// Walls that would leave a cell closed on every side, and so void, stay open.
+    /// Returns every cell that changed.
+    pub fn seal(&mut self, from: Coord, direction: Direction) -> Vec<Coord> {
+        let to = match self.valid_move(from, direction) {
+            Some(to) => to,
+            None => return vec![],
+        };
+        let sides = |cell: Coord| {
+            let cell = self.data[self.coord_to_index(cell)];
+            Direction::cardinals()
+                .iter()
+                .filter(|direction| cell.contains(**direction))
+                .count()
+        };
+        if sides(from) < 2 || sides(to) < 2 {
+            return vec![];
+        }
+        let (a, b) = (self.coord_to_index(from), self.coord_to_index(to));
+        self.data[a].remove(direction);
+        self.data[b].remove(direction.opposite());
+        self.update_corners_around(&[from, to])
+    }
+
+    fn update_corners_around(&mut self, cells: &[Coord]) -> Vec<Coord> {
+        let mut changed = cells.to_vec();
+        for (x, y) in cells.iter().copied() {
+            for nx in x.saturating_sub(1)..=x + 1 {
+                for ny in y.saturating_sub(1)..=y + 1 {
+                    let coord = (nx, ny);
+                    if self.contains(coord)
+                        && update_corner_bits(self, coord)
+                        && !changed.contains(&coord)
+                    {
+                        changed.push(coord);
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Void cells are closed on every side and are not part of the maze, see `Mask`.
+    pub fn is_void(&self, coord: Coord) -> bool {
+        self.checked_coord_to_index(coord)
+            .is_none_or(|index| self.data[index].is_empty())
+    }
+
     pub fn valid_move(&self, start: Coord, direction: Direction) -> Option<Coord> {
         let v = self.data.get(self.coord_to_index(start))?;
         if v.contains(direction) {
@@ -134,48 +237,251 @@ impl DirectionGrid {
         }
     }
 
+    /// Walks the maze from its first non-void cell. Depth first like a recursive walk would
+    /// but with an explicit stack so big maps don't overflow the real one.
     pub fn as_graph(&self) -> Graph {
         let mut graph = Graph::with_capacity(self.data.len(), self.data.len() * 2);
 
-        let origin = self.index_to_coord(0);
+        let origin = self
+            .data
+            .iter()
+            .position(|cell| !cell.is_empty())
+            .unwrap_or(0);
+        let origin = self.index_to_coord(origin);
         graph.add_node(origin);
-        self.add_node(&mut graph, origin);
-
-        graph
-    }
 
-    fn add_node(&self, graph: &mut Graph, origin: Coord) {
-        let v = &self.data[self.coord_to_index(origin)];
-        for direction in Direction::cardinals().iter().copied() {
-            if v.contains(direction) {
-                let (dx, dy) = direction.into_dir();
-                let (nx, ny) = (origin.0 as i32 + dx as i32, origin.1 as i32 + dy as i32);
-                let next = (nx as usize, ny as usize);
+        // each cell along with the next direction to try out of it
+        let mut stack = vec![(origin, 0)];
+        while let Some(top) = stack.last_mut() {
+            let (coord, index) = *top;
+            let direction = match Direction::cardinals().get(index) {
+                Some(direction) => *direction,
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            top.1 += 1;
+            if let Some(next) = self.valid_move(coord, direction) {
                 if !graph.contains_node(next) {
                     graph.add_node(next);
-                    graph.add_edge(origin, next, ());
-                    self.add_node(graph, next);
+                    stack.push((next, 0));
                 }
+                // when `next` was already there this closes a cycle in braided mazes
+                graph.add_edge(coord, next, ());
             }
         }
+
+        graph
     }
 }
 
 pub type Graph = petgraph::graphmap::UnGraphMap<Coord, ()>;
 pub type Coord = (usize, usize);
 
-#[derive(Debug, Copy, Clone)]
+/// Breadth first search out of `from`. Every node that can be reached maps to how many
+/// steps away it is and the node it was reached through. See `bfs_path`.
+pub fn bfs<N>(
+    graph: &petgraph::graphmap::UnGraphMap<N, ()>,
+    from: N,
+) -> std::collections::HashMap<N, (usize, N)>
+where
+    N: petgraph::graphmap::NodeTrait,
+{
+    let mut search = std::collections::HashMap::with_capacity(graph.node_count());
+    if !graph.contains_node(from) {
+        return search;
+    }
+    search.insert(from, (0, from));
+    let mut open = std::collections::VecDeque::new();
+    open.push_back(from);
+    while let Some(node) = open.pop_front() {
+        let distance = search[&node].0 + 1;
+        for next in graph.neighbors(node) {
+            search.entry(next).or_insert_with(|| {
+                open.push_back(next);
+                (distance, node)
+            });
+        }
+    }
+    search
+}
+
+/// The path from where `search` started to `to`, or nothing if `to` wasn't reached.
+pub fn bfs_path<N>(search: &std::collections::HashMap<N, (usize, N)>, to: N) -> Vec<N>
+where
+    N: petgraph::graphmap::NodeTrait,
+{
+    let mut path = Vec::new();
+    let mut current = to;
+    while let Some((distance, parent)) = search.get(&current).copied() {
+        path.push(current);
+        if distance == 0 {
+            break;
+        }
+        current = parent;
+    }
+    path.reverse();
+    path
+}
+
+/// The furthest apart pair of dead ends, or of any two nodes if the graph has fewer than
+/// two dead ends, and the path between them.
+///
+/// Found by repeatedly searching out of the furthest node found by the last search.
+/// Two searches give the exact answer for a perfect maze. Mazes with cycles get a few more
+/// to make up for the first pick sometimes being off, which is close enough for placing
+/// a start and exit.
+pub fn longest_path<N>(graph: &petgraph::graphmap::UnGraphMap<N, ()>) -> Vec<N>
+where
+    N: petgraph::graphmap::NodeTrait,
+{
+    const EXTRA_SEARCHES: usize = 2;
+
+    let is_dead_end = |node: N| graph.neighbors(node).count() == 1;
+    let dead_ends_only = graph
+        .nodes()
+        .filter(|node| is_dead_end(*node))
+        .nth(1)
+        .is_some();
+    let is_candidate = |node: N| !dead_ends_only || is_dead_end(node);
+    // ties go to the largest node so the same graph always gives the same path
+    let furthest = |search: &std::collections::HashMap<N, (usize, N)>| {
+        search
+            .iter()
+            .filter(|(node, _)| is_candidate(**node))
+            .map(|(node, (distance, _))| (*distance, *node))
+            .max()
+    };
+
+    let first = match graph.nodes().find(|node| is_candidate(*node)) {
+        Some(first) => first,
+        None => return Vec::new(),
+    };
+    let from = furthest(&bfs(graph, first)).map_or(first, |(_, node)| node);
+    let mut search = bfs(graph, from);
+    let mut longest = furthest(&search).unwrap_or((0, from));
+
+    let tree = graph.edge_count() + 1 == graph.node_count();
+    if !tree {
+        for _ in 0..EXTRA_SEARCHES {
+            let next = bfs(graph, longest.1);
+            match furthest(&next) {
+                Some(candidate) if candidate.0 > longest.0 => {
+                    longest = candidate;
+                    search = next;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    bfs_path(&search, longest.1)
+}
+
+#[derive(Debug, Clone)]
 pub struct MapGenSettings {
     pub width: usize,
     pub height: usize,
+    /// Restricts the maze to a non-rectangular shape. Must be `width` by `height`.
+    pub mask: Option<Mask>,
+    /// Always generates the same layout when set. Otherwise the seed comes from the run.
+    pub seed: Option<u64>,
+    /// How many grids are stacked up to make the level, each joined to the next by a port.
+    /// Zero is treated the same as one.
+    pub floors: usize,
+    pub generator: Generator,
+    pub braid: BraidSettings,
+    pub rooms: RoomSettings,
     pub programs: ProgramGenSettings,
     pub enemies: EnemyGenSettings,
+    pub difficulty: DifficultyBand,
+    /// How many cells away the player can see, walls permitting. Programs can see further.
+    pub sight: usize,
+    /// How many locked doors to put on the way through each floor. Each one's key is
+    /// somewhere before it.
+    pub doors: usize,
+    /// How many of the branches off of the way through each floor are hidden behind
+    /// walls that aren't really there.
+    pub secrets: usize,
+    /// How often a wall moves somewhere else while the level is being played, if ever.
+    pub shift_every: Option<std::time::Duration>,
+    /// How many of each kind of `Terrain` to scatter over each floor.
+    pub hazards: HazardSettings,
+    /// Which theme out of the tileset file to draw the level with.
+    pub tileset: &'static str,
     pub aesthetic: crate::AestheticShader,
 }
 
+/// The tileset used when a level doesn't ask for one that exists.
+pub const DEFAULT_TILESET: &str = "default";
+
+impl MapGenSettings {
+    /// Builds one floor's grid and rooms, regenerating it until it lands inside of
+    /// `difficulty` or runs out of attempts.
+    pub fn generate<R: rand::Rng>(&self, rng: &mut R) -> (DirectionGrid, Vec<Room>) {
+        if self.difficulty.attempts <= 1 {
+            // nothing to choose between so there's no need to measure it
+            return self.generate_once(rng);
+        }
+
+        let mut best: Option<(f32, DirectionGrid, Vec<Room>)> = None;
+        for _ in 0..self.difficulty.attempts {
+            let (grid, rooms) = self.generate_once(rng);
+            let miss = self.difficulty.miss(&analyze(&grid));
+            if best.as_ref().is_none_or(|(best, _, _)| miss < *best) {
+                best = Some((miss, grid, rooms));
+            }
+            if miss == 0. {
+                break;
+            }
+        }
+
+        let (miss, grid, rooms) = best.unwrap();
+        if miss > 0. {
+            log::warn!(
+                "No {}x{} level fit the difficulty band, using one that missed by {}.",
+                self.width,
+                self.height,
+                miss
+            );
+        }
+        (grid, rooms)
+    }
+
+    fn generate_once<R: rand::Rng>(&self, rng: &mut R) -> (DirectionGrid, Vec<Room>) {
+        let Self { width, height, .. } = *self;
+        let mut grid = self.generator.generate(width, height, rng);
+        match &self.mask {
+            Some(mask) if mask.grid_size() == [width, height] => apply_mask(&mut grid, mask, rng),
+            Some(mask) => log::warn!(
+                "Ignoring {:?} mask for a {}x{} map.",
+                mask.grid_size(),
+                width,
+                height
+            ),
+            None => {}
+        }
+        braid(&mut grid, self.braid, rng);
+        let rooms = carve_rooms(&mut grid, self.rooms, rng);
+        (grid, rooms)
+    }
+}
+
 #[derive(Debug, Copy, Clone, Default)]
 pub struct EnemyGenSettings {
+    /// Wander about at random.
     pub basic_count: usize,
+    /// Go after the player once they've been seen.
+    pub chaser_count: usize,
+    /// Walk back and forth along a route.
+    pub patroller_count: usize,
+    /// Stand guard over a corridor.
+    pub sentry_count: usize,
+    /// Quick and nearsighted.
+    pub crawler_count: usize,
+    /// Slow, but they come through the walls once they're close.
+    pub hunter_count: usize,
 }
 
 #[derive(Debug, Copy, Clone, Default)]
@@ -234,205 +540,78 @@ pub fn apply_not_corner_bit(grid: &mut DirectionGrid) {
     }
 }
 
-pub fn create_batch(
+/// Works out the corner bits of a single cell from scratch, dropping any that aren't
+/// open anymore. Returns whether the cell changed.
+fn update_corner_bits(grid: &mut DirectionGrid, coord: Coord) -> bool {
+    let corners = [
+        (Direction::NEC, Direction::N, Direction::E),
+        (Direction::SEC, Direction::S, Direction::E),
+        (Direction::SWC, Direction::S, Direction::W),
+        (Direction::NWC, Direction::N, Direction::W),
+    ];
+    let index = grid.coord_to_index(coord);
+    let before = grid.data[index];
+    let mut cell = before;
+    for (corner, a, b) in corners.iter().copied() {
+        let open = |from: Direction, to: Direction| {
+            cell.contains(from)
+                && grid
+                    .valid_move(coord, from)
+                    .is_some_and(|next| grid.data[grid.coord_to_index(next)].contains(to))
+        };
+        if open(a, b) && open(b, a) {
+            cell.insert(corner);
+        } else {
+            cell.remove(corner);
+        }
+    }
+    grid.data[index] = cell;
+    cell != before
+}
+
+/// The quad for a single cell, see `create_batch`.
+pub fn tile_quad(
     tile_width: f32,
     tile_height: f32,
     grid: &DirectionGrid,
-    tiles: &std::collections::HashMap<String, crate::UVRect>,
-) -> Vec<solstice_2d::solstice::quad_batch::Quad<solstice_2d::Vertex2D>> {
+    secrets: &Secrets,
+    coord: Coord,
+    tileset: &Tileset,
+) -> solstice_2d::solstice::quad_batch::Quad<solstice_2d::Vertex2D> {
     use solstice_2d::solstice::{quad_batch::Quad, viewport::Viewport};
 
-    let mut quads = Vec::with_capacity(grid.width * grid.height);
-
-    for x in 0..grid.width {
-        for y in 0..grid.height {
-            let index = grid.coord_to_index((x, y));
-            let cell = grid.data[index];
-
-            let name = if cell.is_empty() {
-                "tiles/tile_342.png"
-            } else if cell == Direction::N {
-                "tiles/tile_286.png"
-            } else if cell == Direction::E {
-                "tiles/tile_313.png"
-            } else if cell == Direction::S {
-                "tiles/tile_312.png"
-            } else if cell == Direction::W {
-                "tiles/tile_285.png"
-            } else if cell == Direction::N | Direction::E {
-                "tiles/tile_307.png"
-            } else if cell == Direction::N | Direction::W {
-                "tiles/tile_308.png"
-            } else if cell == Direction::N | Direction::S {
-                "tiles/tile_309.png"
-            } else if cell == Direction::S | Direction::E {
-                "tiles/tile_280.png"
-            } else if cell == Direction::S | Direction::W {
-                "tiles/tile_281.png"
-            } else if cell == Direction::E | Direction::W {
-                "tiles/tile_282.png"
-            } else if cell == Direction::S | Direction::E | Direction::W {
-                "tiles/tile_283.png"
-            } else if cell == Direction::N | Direction::E | Direction::W {
-                "tiles/tile_284.png"
-            } else if cell == Direction::N | Direction::S | Direction::E {
-                "tiles/tile_310.png"
-            } else if cell == Direction::N | Direction::S | Direction::W {
-                "tiles/tile_311.png"
-            } else if cell == Direction::N | Direction::S | Direction::E | Direction::W {
-                "tiles/tile_341.png"
-            } else if cell == Direction::NEC | Direction::N | Direction::E {
-                "tiles/tile_314.png"
-            } else if cell == Direction::NWC | Direction::N | Direction::W {
-                "tiles/tile_315.png"
-            } else if cell == Direction::SEC | Direction::S | Direction::E {
-                "tiles/tile_287.png"
-            } else if cell == Direction::SWC | Direction::S | Direction::W {
-                "tiles/tile_288.png"
-            } else if cell == Direction::NEC | Direction::N | Direction::E | Direction::W {
-                "tiles/tile_419.png"
-            } else if cell == Direction::NWC | Direction::N | Direction::E | Direction::W {
-                "tiles/tile_420.png"
-            } else if cell == Direction::SEC | Direction::S | Direction::E | Direction::W {
-                "tiles/tile_392.png"
-            } else if cell == Direction::SWC | Direction::S | Direction::E | Direction::W {
-                "tiles/tile_393.png"
-            } else if cell == Direction::NEC | Direction::N | Direction::S | Direction::E {
-                "tiles/tile_417.png"
-            } else if cell == Direction::SEC | Direction::N | Direction::S | Direction::E {
-                "tiles/tile_390.png"
-            } else if cell == Direction::NWC | Direction::N | Direction::S | Direction::W {
-                "tiles/tile_418.png"
-            } else if cell == Direction::SWC | Direction::N | Direction::S | Direction::W {
-                "tiles/tile_391.png"
-            } else if cell
-                == Direction::NEC | Direction::NWC | Direction::N | Direction::E | Direction::W
-            {
-                "tiles/tile_366.png"
-            } else if cell
-                == Direction::SEC | Direction::SWC | Direction::S | Direction::E | Direction::W
-            {
-                "tiles/tile_365.png"
-            } else if cell
-                == Direction::NEC | Direction::SEC | Direction::N | Direction::S | Direction::E
-            {
-                "tiles/tile_338.png"
-            } else if cell
-                == Direction::NWC | Direction::SWC | Direction::N | Direction::S | Direction::W
-            {
-                "tiles/tile_339.png"
-            } else if cell
-                == Direction::NEC
-                    | Direction::NWC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_336.png"
-            } else if cell
-                == Direction::SEC
-                    | Direction::SWC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_337.png"
-            } else if cell
-                == Direction::NWC
-                    | Direction::SWC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_363.png"
-            } else if cell
-                == Direction::NEC
-                    | Direction::SEC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_364.png"
-            } else if cell
-                == Direction::SWC
-                    | Direction::NWC
-                    | Direction::NEC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_334.png"
-            } else if cell
-                == Direction::SEC
-                    | Direction::NWC
-                    | Direction::NEC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_335.png"
-            } else if cell
-                == Direction::SEC
-                    | Direction::SWC
-                    | Direction::NEC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_362.png"
-            } else if cell
-                == Direction::SEC
-                    | Direction::SWC
-                    | Direction::NWC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_361.png"
-            } else if cell
-                == Direction::SEC
-                    | Direction::SWC
-                    | Direction::NWC
-                    | Direction::NEC
-                    | Direction::N
-                    | Direction::S
-                    | Direction::E
-                    | Direction::W
-            {
-                "tiles/tile_340.png"
-            } else {
-                panic!("couldn't match cell to tile. {:?}", cell);
-            };
-
-            let tile = match tiles.get(name).cloned() {
-                None => panic!("couldn't find {}", name),
-                Some(tile) => tile,
-            };
-            let quad = Quad::from(Viewport::new(
-                x as f32 * tile_width,
-                y as f32 * tile_height,
-                tile_width,
-                tile_height,
-            ))
-            .zip(tile.uvs.into())
-            .map(|((x, y), (s, t))| solstice_2d::Vertex2D {
-                position: [x, y],
-                color: [1., 1., 1., 1.],
-                uv: [s, t],
-            });
-            quads.push(quad);
-        }
-    }
+    let (x, y) = coord;
+    let (tile, color) = tileset.tile(secrets.shown(grid, coord));
+    Quad::from(Viewport::new(
+        x as f32 * tile_width,
+        y as f32 * tile_height,
+        tile_width,
+        tile_height,
+    ))
+    .zip(tile.uvs.into())
+    .map(|((x, y), (s, t))| solstice_2d::Vertex2D {
+        position: [x, y],
+        color,
+        uv: [s, t],
+    })
+}
 
-    quads
+/// One quad per cell, in the same order as the cells so that a single tile can be
+/// swapped out later. Secret passages are drawn shut.
+pub fn create_batch(
+    tile_width: f32,
+    tile_height: f32,
+    grid: &DirectionGrid,
+    secrets: &Secrets,
+    tileset: &Tileset,
+) -> Vec<solstice_2d::solstice::quad_batch::Quad<solstice_2d::Vertex2D>> {
+    // void cells still get a quad so that quads stay in step with cells
+    (0..grid.data.len())
+        .map(|index| {
+            let coord = grid.index_to_coord(index);
+            tile_quad(tile_width, tile_height, grid, secrets, coord, tileset)
+        })
+        .collect()
 }
 
 pub fn neighbor_coord(
@@ -456,8 +635,11 @@ pub fn neighbor_coord_mult(
     Ok((nx, ny))
 }
 
-#[derive(Copy, Clone)]
-enum Selector {
+/// How the growing tree algorithm picks the next cell to grow from.
+/// Always picking the newest cell gives long, winding corridors (a recursive backtracker)
+/// while picking at random gives short, bushy branches (similar to Prim's).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Selector {
     Random,
     Newest,
     Middle,
@@ -475,12 +657,73 @@ impl Selector {
     }
 }
 
-#[derive(Default)]
+/// Relative likelihood of each `Selector` being used per step of the growing tree.
+/// Missing fields deserialize to zero. If every weight is zero, `Newest` is used.
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Weights {
-    random: f32,
-    newest: f32,
-    middle: f32,
-    oldest: f32,
+    #[serde(default)]
+    pub random: f32,
+    #[serde(default)]
+    pub newest: f32,
+    #[serde(default)]
+    pub middle: f32,
+    #[serde(default)]
+    pub oldest: f32,
+}
+
+impl Weights {
+    pub const NONE: Weights = Weights {
+        random: 0.,
+        newest: 0.,
+        middle: 0.,
+        oldest: 0.,
+    };
+
+    /// Long corridors with few dead ends.
+    pub fn corridors() -> Self {
+        Self {
+            random: 0.1,
+            newest: 1.,
+            ..Self::NONE
+        }
+    }
+
+    /// Short branches with lots of dead ends.
+    pub fn bushy() -> Self {
+        Self {
+            random: 1.,
+            oldest: 0.5,
+            ..Self::NONE
+        }
+    }
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            random: crate::lerp(self.random, other.random, t),
+            newest: crate::lerp(self.newest, other.newest, t),
+            middle: crate::lerp(self.middle, other.middle, t),
+            oldest: crate::lerp(self.oldest, other.oldest, t),
+        }
+    }
+
+    pub fn entries(&self) -> [(Selector, f32); 4] {
+        [
+            (Selector::Random, self.random),
+            (Selector::Newest, self.newest),
+            (Selector::Middle, self.middle),
+            (Selector::Oldest, self.oldest),
+        ]
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            random: 1.,
+            newest: 1.,
+            ..Self::NONE
+        }
+    }
 }
 
 fn growing_tree<R>(
@@ -492,19 +735,19 @@ fn growing_tree<R>(
 where
     R: rand::Rng,
 {
-    let choices = [
-        Selector::Random,
-        Selector::Newest,
-        Selector::Middle,
-        Selector::Oldest,
-    ];
-    let dist = rand_distr::WeightedIndex::new(&[
-        weights.random,
-        weights.newest,
-        weights.middle,
-        weights.oldest,
-    ])
-    .unwrap();
+    let entries = weights.entries();
+    let dist = rand_distr::WeightedIndex::new(entries.iter().map(|(_, weight)| weight.max(0.)))
+        .or_else(|_| {
+            log::warn!("Invalid growing tree weights {:?}. Using newest.", weights);
+            rand_distr::WeightedIndex::new(entries.iter().map(|(selector, _)| {
+                if *selector == Selector::Newest {
+                    1.
+                } else {
+                    0.
+                }
+            }))
+        })
+        .unwrap();
 
     let mut cells: Vec<(usize, usize)> = Vec::with_capacity((width * height) / 2);
     cells.push((rng.gen_range(0..width), rng.gen_range(0..height)));
@@ -513,7 +756,7 @@ where
     let mut cardinal_directions = Direction::cardinals();
 
     while !cells.is_empty() {
-        let selector = choices[rand::distributions::Distribution::sample(&dist, rng)];
+        let (selector, _) = entries[rand::distributions::Distribution::sample(&dist, rng)];
         let index = selector.select(rng, cells.len() - 1);
         let (x, y) = cells[index];
 
@@ -548,6 +791,19 @@ where
     grid
 }
 
+/// Mazes for the tests of more than one module.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    /// A corridor along the top with a bent corridor hanging down off of either side of it.
+    pub const MAZE: &str = "
++-+-+-+-+
+|       |
++-+ +-+ +
+|   |   |
++-+-+-+-+
+";
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -574,6 +830,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn growing_tree_weights_test() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let grid = growing_tree(4, 4, Weights::NONE, &mut rng);
+        for cell in grid {
+            assert_ne!(cell, BitFlags::empty());
+        }
+
+        let weights: Weights = serde_json::from_str(r#"{ "oldest": 2.0 }"#).unwrap();
+        assert_eq!(
+            weights,
+            Weights {
+                oldest: 2.0,
+                ..Weights::NONE
+            }
+        );
+    }
+
     #[test]
     fn graph_test() {
         use rand::SeedableRng;
@@ -617,6 +892,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn longest_path_test() {
+        let all = BitFlags::from(Direction::N) | Direction::E | Direction::S | Direction::W;
+        let mut grid = Grid::filled(3, 3, all);
+        for (index, cell) in grid.data.iter_mut().enumerate() {
+            let (x, y) = d1_to_d2(index, 3);
+            if x == 0 {
+                cell.remove(Direction::W);
+            }
+            if x == 2 {
+                cell.remove(Direction::E);
+            }
+            if y == 0 {
+                cell.remove(Direction::N);
+            }
+            if y == 2 {
+                cell.remove(Direction::S);
+            }
+        }
+
+        // no dead ends and full of cycles: the furthest apart nodes are opposite corners
+        let graph = grid.as_graph();
+        assert_eq!(graph.edge_count(), 12);
+        let path = longest_path(&graph);
+        assert_eq!(path.len(), 5);
+        let (first, last) = (path[0], path[path.len() - 1]);
+        assert_eq!((first.0 as i32 - last.0 as i32).abs(), 2);
+        assert_eq!((first.1 as i32 - last.1 as i32).abs(), 2);
+    }
+
+    #[test]
+    fn large_map_test() {
+        // long winding corridors used to overflow the stack while building the graph
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut grid = Generator::RecursiveBacktracker.generate(256, 256, &mut rng);
+        let graph = grid.as_graph();
+        assert_eq!(graph.node_count(), 256 * 256);
+
+        // in a perfect maze nothing is further from one end of the path than the other end
+        let path = longest_path(&graph);
+        let search = bfs(&graph, path[0]);
+        let furthest = search.values().map(|(distance, _)| *distance).max();
+        assert_eq!(furthest, Some(path.len() - 1));
+        assert_eq!(bfs_path(&search, path[path.len() - 1]), path);
+
+        braid(
+            &mut grid,
+            BraidSettings {
+                dead_end_ratio: 0.5,
+                extra_loops: 256,
+            },
+            &mut rng,
+        );
+        let graph = grid.as_graph();
+        let path = longest_path(&graph);
+        assert!(path.len() > 256);
+        assert!(path
+            .windows(2)
+            .all(|pair| graph.contains_edge(pair[0], pair[1])));
+    }
+
     #[test]
     fn neighbor_test() {
         let origin = (5, 4);
@@ -648,4 +985,41 @@ mod tests {
         assert_eq!(grid.data[grid.coord_to_index((1, 0))], BitFlags::empty());
         assert_ne!(grid.data[grid.coord_to_index((0, 1))], BitFlags::empty());
     }
+
+    #[test]
+    fn carve_test() {
+        const MAZE: &str = "
++-+-+-+
+|   | |
++ +-+-+
+|   | |
++-+-+-+
+";
+        let (mut grid, _) = parse_ascii(MAZE).unwrap();
+
+        // closing the loop opens up the corner in the middle of it
+        let mut changed = grid.carve((1, 0), Direction::S);
+        changed.sort_unstable();
+        assert_eq!(changed, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert!(grid.data[grid.coord_to_index((0, 0))].contains(Direction::SEC));
+        assert!(grid.data[grid.coord_to_index((1, 1))].contains(Direction::NWC));
+
+        let mut fresh = grid.clone();
+        apply_not_corner_bit(&mut fresh);
+        assert_eq!(fresh.data, grid.data);
+
+        // already open, out of the grid and into the void
+        assert!(grid.carve((0, 0), Direction::E).is_empty());
+        assert!(grid.carve((0, 0), Direction::N).is_empty());
+        assert!(grid.carve((1, 1), Direction::E).is_empty());
+
+        // and back again
+        let mut changed = grid.seal((1, 0), Direction::S);
+        changed.sort_unstable();
+        assert_eq!(changed, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert_eq!(grid.data, parse_ascii(MAZE).unwrap().0.data);
+        // the last way in or out of a cell, and a wall that's already there
+        assert!(grid.seal((0, 0), Direction::E).is_empty());
+        assert!(grid.seal((0, 0), Direction::N).is_empty());
+    }
 }
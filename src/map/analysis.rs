@@ -0,0 +1,253 @@
+use super::{Coord, DirectionGrid, Graph};
+
+/// Measurements of how hard a maze is likely to be to solve.
+/// All of them only count the cells reachable in `Graph`, so void cells are left out.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub cells: usize,
+    pub dead_ends: usize,
+    /// The fraction of cells where the maze splits three or four ways.
+    pub branching_factor: f32,
+    /// How much of the maze the solution walks through, from 0 to 1.
+    pub solution_ratio: f32,
+    /// The average length of the dead end branches hanging off the solution. Long, winding
+    /// dead ends flow like a river and waste a lot of the player's time, short ones don't.
+    pub river: f32,
+    /// The average number of steps between two junctions or dead ends.
+    pub corridor_length: f32,
+}
+
+impl Metrics {
+    pub fn new(graph: &Graph, path: &[Coord]) -> Self {
+        let degree = |node: Coord| graph.neighbors(node).count();
+        let cells = graph.node_count();
+        if cells == 0 {
+            return Self::default();
+        }
+        let dead_ends = graph.nodes().filter(|node| degree(*node) == 1).count();
+        let junctions = graph.nodes().filter(|node| degree(*node) > 2).count();
+
+        // follows a corridor from `from` through `next` until it ends somewhere interesting
+        let walk = |from: Coord, next: Coord, stop: &dyn Fn(Coord) -> bool| {
+            let (mut previous, mut current, mut length) = (from, next, 1);
+            while degree(current) == 2 && !stop(current) && current != from {
+                let onward = graph.neighbors(current).find(|node| *node != previous);
+                match onward {
+                    Some(onward) => {
+                        previous = current;
+                        current = onward;
+                        length += 1;
+                    }
+                    None => break,
+                }
+            }
+            length
+        };
+
+        let (mut corridors, mut corridor_steps) = (0, 0);
+        for node in graph.nodes().filter(|node| degree(*node) != 2) {
+            for next in graph.neighbors(node) {
+                corridors += 1;
+                corridor_steps += walk(node, next, &|_| false);
+            }
+        }
+        // a maze that's nothing but one big loop is all corridor
+        let corridor_length = if corridors == 0 {
+            cells as f32
+        } else {
+            corridor_steps as f32 / corridors as f32
+        };
+
+        let path_cells = path.iter().collect::<std::collections::HashSet<_>>();
+        let on_path = |node: Coord| path_cells.contains(&node);
+        let branches = graph
+            .nodes()
+            .filter(|node| degree(*node) == 1 && !on_path(*node))
+            .filter_map(|node| {
+                graph
+                    .neighbors(node)
+                    .next()
+                    .map(|next| walk(node, next, &on_path))
+            })
+            .collect::<Vec<_>>();
+        let river = if branches.is_empty() {
+            0.
+        } else {
+            branches.iter().sum::<usize>() as f32 / branches.len() as f32
+        };
+
+        Self {
+            cells,
+            dead_ends,
+            branching_factor: junctions as f32 / cells as f32,
+            solution_ratio: path.len() as f32 / cells as f32,
+            river,
+            corridor_length,
+        }
+    }
+}
+
+/// Measures a single grid, taking its longest path as the solution.
+pub fn analyze(grid: &DirectionGrid) -> Metrics {
+    let graph = grid.as_graph();
+    let path = super::longest_path(&graph);
+    Metrics::new(&graph, &path)
+}
+
+/// How many steps each of `coords` is from the nearest cell of `path`.
+/// `None` for coords that can't reach the path at all.
+pub fn path_distances(graph: &Graph, path: &[Coord], coords: &[Coord]) -> Vec<Option<usize>> {
+    let mut distances = std::collections::HashMap::with_capacity(graph.node_count());
+    let mut open = std::collections::VecDeque::new();
+    for node in path
+        .iter()
+        .copied()
+        .filter(|node| graph.contains_node(*node))
+    {
+        distances.insert(node, 0);
+        open.push_back(node);
+    }
+    while let Some(node) = open.pop_front() {
+        let distance = distances[&node];
+        for next in graph.neighbors(node) {
+            distances.entry(next).or_insert_with(|| {
+                open.push_back(next);
+                distance + 1
+            });
+        }
+    }
+    coords
+        .iter()
+        .map(|coord| distances.get(coord).copied())
+        .collect()
+}
+
+/// Inclusive limits for one of the `Metrics`. Unbounded by default.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self {
+            min: f32::NEG_INFINITY,
+            max: f32::INFINITY,
+        }
+    }
+}
+
+impl Bounds {
+    pub fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    /// How far outside of the bounds `value` is, relative to the size of the bounds.
+    fn miss(&self, value: f32) -> f32 {
+        let size = (self.max - self.min).abs();
+        let size = if size.is_finite() && size > 0. {
+            size
+        } else {
+            1.
+        };
+        if value < self.min {
+            (self.min - value) / size
+        } else if value > self.max {
+            (value - self.max) / size
+        } else {
+            0.
+        }
+    }
+}
+
+/// Generated levels get thrown away and regenerated until their `Metrics` land inside
+/// of the band. The default accepts anything.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct DifficultyBand {
+    /// Dead ends per cell.
+    pub dead_end_ratio: Bounds,
+    pub branching_factor: Bounds,
+    pub solution_ratio: Bounds,
+    pub river: Bounds,
+    pub corridor_length: Bounds,
+    /// How many levels to generate at most. The closest miss is used if none of them fit.
+    pub attempts: usize,
+}
+
+impl DifficultyBand {
+    /// Zero when `metrics` falls inside the band, bigger the further outside it is.
+    pub fn miss(&self, metrics: &Metrics) -> f32 {
+        let dead_end_ratio = if metrics.cells == 0 {
+            0.
+        } else {
+            metrics.dead_ends as f32 / metrics.cells as f32
+        };
+        self.dead_end_ratio.miss(dead_end_ratio)
+            + self.branching_factor.miss(metrics.branching_factor)
+            + self.solution_ratio.miss(metrics.solution_ratio)
+            + self.river.miss(metrics.river)
+            + self.corridor_length.miss(metrics.corridor_length)
+    }
+
+    pub fn contains(&self, metrics: &Metrics) -> bool {
+        self.miss(metrics) == 0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::MAZE;
+    use super::*;
+
+    #[test]
+    fn metrics_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let graph = grid.as_graph();
+        let path = super::super::longest_path(&graph);
+        assert_eq!(path.len(), 7);
+
+        let metrics = Metrics::new(&graph, &path);
+        assert_eq!(metrics.cells, 8);
+        assert_eq!(metrics.dead_ends, 3);
+        assert_eq!(metrics.branching_factor, 1. / 8.);
+        assert_eq!(metrics.solution_ratio, 7. / 8.);
+        // the top left corner is the only dead end off of the solution
+        assert_eq!(metrics.river, 1.);
+        // corridors of 1, 2 and 4 steps out of the junction, counted once from each end
+        assert_eq!(metrics.corridor_length, (1. + 2. + 4.) * 2. / 6.);
+        assert_eq!(analyze(&grid), metrics);
+    }
+
+    #[test]
+    fn path_distances_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let graph = grid.as_graph();
+        let path = [(0, 0), (1, 0), (2, 0), (3, 0)];
+        let distances = path_distances(&graph, &path, &[(1, 1), (0, 1), (3, 1), (9, 9)]);
+        assert_eq!(distances, vec![Some(1), Some(2), Some(1), None]);
+    }
+
+    #[test]
+    fn band_test() {
+        let metrics = Metrics {
+            cells: 10,
+            dead_ends: 2,
+            solution_ratio: 0.5,
+            ..Default::default()
+        };
+        assert!(DifficultyBand::default().contains(&metrics));
+
+        let band = DifficultyBand {
+            solution_ratio: Bounds::new(0.6, 0.8),
+            ..Default::default()
+        };
+        assert!(!band.contains(&metrics));
+        assert!((band.miss(&metrics) - 0.5).abs() < 1e-5);
+        let closer = Metrics {
+            solution_ratio: 0.55,
+            ..metrics
+        };
+        assert!(band.miss(&closer) < band.miss(&metrics));
+    }
+}
@@ -0,0 +1,208 @@
+use super::{Coord, Direction, DirectionGrid, Grid};
+use enumflags2::BitFlags;
+
+/// Extra things to draw into the cells of `DirectionGrid::to_ascii_with`.
+/// When several land on one cell the earliest field listed here wins.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Overlay<'a> {
+    /// Drawn as `@`.
+    pub player: Option<Coord>,
+    /// Drawn as `E`.
+    pub enemies: &'a [Coord],
+    /// Drawn as `X`.
+    pub exit: Option<Coord>,
+    /// Cells that are still `false` are drawn as `#`.
+    pub seen: Option<&'a Grid<bool>>,
+    /// Drawn as `.`, usually the longest path.
+    pub path: &'a [Coord],
+}
+
+impl Overlay<'_> {
+    fn marker(&self, coord: Coord) -> char {
+        let unseen = self.seen.is_some_and(|seen| {
+            seen.checked_coord_to_index(coord)
+                .is_some_and(|index| !seen.data[index])
+        });
+        if self.player == Some(coord) {
+            '@'
+        } else if self.enemies.contains(&coord) {
+            'E'
+        } else if self.exit == Some(coord) {
+            'X'
+        } else if unseen {
+            '#'
+        } else if self.path.contains(&coord) {
+            '.'
+        } else {
+            ' '
+        }
+    }
+}
+
+impl DirectionGrid {
+    /// Draws the grid with `+` corners, `|` and `-` walls and a space for every opening.
+    /// `parse_ascii` reads it back in.
+    pub fn to_ascii(&self) -> String {
+        self.to_ascii_with(&Overlay::default())
+    }
+
+    pub fn to_ascii_with(&self, overlay: &Overlay) -> String {
+        let mut out = String::with_capacity((self.width * 2 + 2) * (self.height * 2 + 1));
+        let horizontal_wall = |out: &mut String, y: usize| {
+            out.push('+');
+            for x in 0..self.width {
+                let open =
+                    y > 0 && self.data[self.coord_to_index((x, y - 1))].contains(Direction::S);
+                out.push(if open { ' ' } else { '-' });
+                out.push('+');
+            }
+            out.push('\n');
+        };
+
+        for y in 0..self.height {
+            horizontal_wall(&mut out, y);
+            out.push('|');
+            for x in 0..self.width {
+                let cell = self.data[self.coord_to_index((x, y))];
+                out.push(overlay.marker((x, y)));
+                out.push(if cell.contains(Direction::E) {
+                    ' '
+                } else {
+                    '|'
+                });
+            }
+            out.push('\n');
+        }
+        horizontal_wall(&mut out, self.height);
+
+        out
+    }
+}
+
+impl std::fmt::Display for DirectionGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_ascii())
+    }
+}
+
+/// Reads a grid drawn like `DirectionGrid::to_ascii` along with whatever character
+/// was drawn inside each cell. Blank lines around the grid are ignored.
+/// Corner bits aren't part of the drawing so aren't set, see `apply_not_corner_bit`.
+pub fn parse_ascii(template: &str) -> eyre::Result<(DirectionGrid, Grid<char>)> {
+    let lines = template
+        .lines()
+        .map(|line| line.trim_end().chars().collect::<Vec<_>>())
+        .skip_while(Vec::is_empty)
+        .collect::<Vec<_>>();
+    let rows = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map(|i| i + 1);
+    let rows = rows.ok_or_else(|| eyre::Report::msg("Grid template is empty."))?;
+    let columns = lines[0].len();
+    if rows < 3 || rows % 2 == 0 || columns < 3 || columns % 2 == 0 {
+        return Err(eyre::Report::msg(format!(
+            "Grid template is {}x{} characters but both must be odd and at least 3.",
+            columns, rows
+        )));
+    }
+
+    let at = |x: usize, y: usize| lines[y].get(x).copied().unwrap_or(' ');
+    let is_open = |x: usize, y: usize, wall: char| match at(x, y) {
+        ' ' => Ok(true),
+        c if c == wall => Ok(false),
+        c => Err(eyre::Report::msg(format!(
+            "Expected '{}' or a space in grid template at line {}, column {} but found '{}'.",
+            wall,
+            y + 1,
+            x + 1,
+            c
+        ))),
+    };
+
+    let (width, height) = (columns / 2, rows / 2);
+    let mut grid = Grid::filled(width, height, BitFlags::<Direction>::empty());
+    let mut markers = Grid::filled(width, height, ' ');
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = (x * 2 + 1, y * 2 + 1);
+            if is_open(cx + 1, cy, '|')? && x + 1 < width {
+                grid.make_open((x, y), Direction::E);
+            }
+            if is_open(cx, cy + 1, '-')? && y + 1 < height {
+                grid.make_open((x, y), Direction::S);
+            }
+            markers.data[markers.coord_to_index((x, y))] = at(cx, cy);
+        }
+    }
+
+    Ok((grid, markers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{Generator, MazeGenerator};
+    use rand::SeedableRng;
+
+    #[test]
+    fn round_trip_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for generator in [Generator::default(), Generator::Kruskal, Generator::Eller].iter() {
+            let grid = generator.generate(9, 6, &mut rng);
+            let (parsed, markers) = parse_ascii(&grid.to_ascii()).unwrap();
+            assert_eq!(parsed.grid_size(), grid.grid_size());
+            assert_eq!(parsed.data, grid.data);
+            assert!(markers.data.iter().all(|marker| *marker == ' '));
+        }
+    }
+
+    #[test]
+    fn overlay_test() {
+        let (grid, _) = parse_ascii(
+            "
++-+-+-+
+|     |
++-+-+ +
+|     |
++-+-+-+
+",
+        )
+        .unwrap();
+        let mut seen = Grid::filled(3, 2, true);
+        seen.data[3] = false;
+        let overlay = Overlay {
+            player: Some((0, 0)),
+            enemies: &[(2, 1)],
+            exit: Some((1, 1)),
+            seen: Some(&seen),
+            path: &[(0, 0), (1, 0), (2, 0), (2, 1), (1, 1)],
+        };
+        let expected = "
++-+-+-+
+|@ . .|
++-+-+ +
+|# X E|
++-+-+-+
+";
+        assert_eq!(grid.to_ascii_with(&overlay), &expected[1..]);
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(7);
+        let grid = Generator::RecursiveBacktracker.generate(6, 4, &mut rng);
+        let expected = "
++-+-+-+-+-+-+
+| |       | |
++ + +-+ + + +
+| | | | | | |
++ + + +-+ + +
+| | |     | |
++ + +-+-+-+ +
+|           |
++-+-+-+-+-+-+
+";
+        assert_eq!(grid.to_string(), &expected[1..]);
+    }
+}
@@ -0,0 +1,116 @@
+use super::{Coord, Direction, DirectionGrid};
+use rand::seq::SliceRandom;
+
+/// Post-processing that turns a perfect maze into an imperfect one by adding cycles.
+/// The default leaves the maze untouched.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct BraidSettings {
+    /// The fraction (0 to 1) of dead ends that get a wall knocked out.
+    pub dead_end_ratio: f32,
+    /// Walls removed at random, anywhere in the maze.
+    pub extra_loops: usize,
+}
+
+pub fn is_dead_end(grid: &DirectionGrid, coord: Coord) -> bool {
+    let cell = grid.data[grid.coord_to_index(coord)];
+    let openings = Direction::cardinals()
+        .iter()
+        .filter(|direction| cell.contains(**direction))
+        .count();
+    openings == 1
+}
+
+pub fn dead_ends(grid: &DirectionGrid) -> impl Iterator<Item = Coord> + '_ {
+    grid.iter()
+        .map(|(_, coord)| coord)
+        .filter(move |coord| is_dead_end(grid, *coord))
+}
+
+fn closed_neighbors(grid: &DirectionGrid, coord: Coord) -> impl Iterator<Item = Direction> + '_ {
+    let cell = grid.data[grid.coord_to_index(coord)];
+    let void = cell.is_empty();
+    IntoIterator::into_iter(Direction::cardinals()).filter(move |direction| {
+        let neighbor = grid.neighbor(coord, *direction);
+        !void && !cell.contains(*direction) && neighbor.is_some_and(|n| !grid.is_void(n))
+    })
+}
+
+pub fn braid<R: rand::Rng>(grid: &mut DirectionGrid, settings: BraidSettings, rng: &mut R) {
+    let ratio = settings.dead_end_ratio.clamp(0., 1.);
+    let mut candidates = dead_ends(grid).collect::<Vec<_>>();
+    let count = (candidates.len() as f32 * ratio).round() as usize;
+    candidates.shuffle(rng);
+
+    for coord in candidates.into_iter().take(count) {
+        // opening an earlier dead end may have already fixed this one
+        if !is_dead_end(grid, coord) {
+            continue;
+        }
+
+        let mut directions = closed_neighbors(grid, coord).collect::<Vec<_>>();
+        directions.shuffle(rng);
+        // joining two dead ends removes both with a single wall
+        let best = directions.iter().copied().find(|direction| {
+            let neighbor = grid.neighbor(coord, *direction).unwrap();
+            is_dead_end(grid, neighbor)
+        });
+        if let Some(direction) = best.or_else(|| directions.first().copied()) {
+            grid.make_open(coord, direction);
+        }
+    }
+
+    let mut loops = 0;
+    let mut attempts = settings.extra_loops * 4;
+    while loops < settings.extra_loops && attempts > 0 {
+        attempts -= 1;
+        let index = rng.gen_range(0..grid.data.len());
+        let coord = grid.index_to_coord(index);
+        let directions = closed_neighbors(grid, coord).collect::<Vec<_>>();
+        if let Some(direction) = directions.choose(rng) {
+            grid.make_open(coord, *direction);
+            loops += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::MazeGenerator;
+    use rand::SeedableRng;
+
+    #[test]
+    fn braid_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut grid = crate::map::Generator::Kruskal.generate(8, 8, &mut rng);
+        assert!(dead_ends(&grid).count() > 0);
+
+        let settings = BraidSettings {
+            dead_end_ratio: 1.,
+            extra_loops: 0,
+        };
+        braid(&mut grid, settings, &mut rng);
+        assert_eq!(dead_ends(&grid).count(), 0);
+    }
+
+    #[test]
+    fn extra_loops_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut grid = crate::map::Generator::Kruskal.generate(8, 8, &mut rng);
+        let openings = |grid: &DirectionGrid| {
+            grid.data
+                .iter()
+                .map(|cell| cell.bits().count_ones() as usize)
+                .sum::<usize>()
+                / 2
+        };
+        let before = openings(&grid);
+
+        let settings = BraidSettings {
+            dead_end_ratio: 0.,
+            extra_loops: 5,
+        };
+        braid(&mut grid, settings, &mut rng);
+        assert_eq!(openings(&grid), before + 5);
+    }
+}
@@ -0,0 +1,171 @@
+use super::{Coord, Direction, DirectionGrid, Grid};
+
+/// The distance given to cells a `Grid::dijkstra_map` can't reach.
+pub const UNREACHABLE: u32 = u32::MAX;
+
+impl<T> Grid<T> {
+    /// The cells on each side of `coord` that are inside of the grid, walls or not.
+    pub fn neighbors(&self, coord: Coord) -> impl Iterator<Item = Coord> + '_ {
+        IntoIterator::into_iter(Direction::cardinals())
+            .filter_map(move |direction| self.neighbor(coord, direction))
+    }
+
+    /// Every cell that can be reached from `from`, only stepping from one cell to another
+    /// where `can_step` allows it.
+    pub fn flood_fill<F>(&self, from: Coord, mut can_step: F) -> Grid<bool>
+    where
+        F: FnMut(Coord, Coord) -> bool,
+    {
+        let mut filled = Grid::filled(self.width, self.height, false);
+        let index = match self.checked_coord_to_index(from) {
+            Some(index) => index,
+            None => return filled,
+        };
+        filled.data[index] = true;
+        let mut open = vec![from];
+        while let Some(coord) = open.pop() {
+            for next in self.neighbors(coord) {
+                let index = self.coord_to_index(next);
+                if !filled.data[index] && can_step(coord, next) {
+                    filled.data[index] = true;
+                    open.push(next);
+                }
+            }
+        }
+        filled
+    }
+
+    /// How far every cell is from the closest of `sources`, `UNREACHABLE` for cells that
+    /// can't be reached at all. `cost` is what it takes to step from one cell to the next,
+    /// `None` when the step isn't allowed.
+    ///
+    /// Enemies can roll downhill to chase whatever the sources are, or uphill to get away.
+    pub fn dijkstra_map<F>(&self, sources: &[Coord], mut cost: F) -> Grid<u32>
+    where
+        F: FnMut(Coord, Coord) -> Option<u32>,
+    {
+        use std::{cmp::Reverse, collections::BinaryHeap};
+
+        let mut distances = Grid::filled(self.width, self.height, UNREACHABLE);
+        let mut open = BinaryHeap::new();
+        for source in sources.iter().copied() {
+            if let Some(index) = self.checked_coord_to_index(source) {
+                distances.data[index] = 0;
+                open.push(Reverse((0, index)));
+            }
+        }
+        while let Some(Reverse((distance, index))) = open.pop() {
+            if distance > distances.data[index] {
+                // already got here a shorter way
+                continue;
+            }
+            let coord = self.index_to_coord(index);
+            for next in self.neighbors(coord) {
+                if let Some(step) = cost(coord, next) {
+                    let next = self.coord_to_index(next);
+                    let distance = distance.saturating_add(step);
+                    if distance < distances.data[next] {
+                        distances.data[next] = distance;
+                        open.push(Reverse((distance, next)));
+                    }
+                }
+            }
+        }
+        distances
+    }
+}
+
+impl DirectionGrid {
+    /// The cells `coord` has an open side into.
+    pub fn open_neighbors(&self, coord: Coord) -> impl Iterator<Item = Coord> + '_ {
+        IntoIterator::into_iter(Direction::cardinals())
+            .filter_map(move |direction| self.valid_move(coord, direction))
+    }
+
+    fn is_open_between(&self, from: Coord, to: Coord) -> bool {
+        self.open_neighbors(from).any(|neighbor| neighbor == to)
+    }
+
+    /// Every cell the maze connects to `from`.
+    pub fn reachable(&self, from: Coord) -> Grid<bool> {
+        self.flood_fill(from, |from, to| self.is_open_between(from, to))
+    }
+
+    /// How many steps through the maze every cell is from the closest of `sources`.
+    pub fn distances(&self, sources: &[Coord]) -> Grid<u32> {
+        self.dijkstra_map(sources, |from, to| {
+            if self.is_open_between(from, to) {
+                Some(1)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Grid<u32> {
+    /// The neighbor of `coord` that's the furthest downhill, if any of them are.
+    /// Following it over and over leads to the closest source.
+    pub fn downhill(&self, grid: &DirectionGrid, coord: Coord) -> Option<Coord> {
+        let here = self.data[self.checked_coord_to_index(coord)?];
+        grid.open_neighbors(coord)
+            .map(|next| (self.data[self.coord_to_index(next)], next))
+            .filter(|(distance, _)| *distance < here)
+            .min()
+            .map(|(_, next)| next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "
++-+-+-+
+|     |
++-+-+ +
+|     |
++-+-+-+
+";
+
+    #[test]
+    fn distances_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let distances = grid.distances(&[(0, 0)]);
+        assert_eq!(&distances.data[..], &[0, 1, 2, 5, 4, 3][..]);
+
+        // meeting in the middle
+        let distances = grid.distances(&[(0, 0), (0, 1)]);
+        assert_eq!(&distances.data[..], &[0, 1, 2, 0, 1, 2][..]);
+        assert_eq!(distances.downhill(&grid, (2, 1)), Some((1, 1)));
+        assert_eq!(distances.downhill(&grid, (0, 1)), None);
+
+        // walls don't matter when the cost says so
+        let open = grid.dijkstra_map(&[(0, 0)], |_, _| Some(2));
+        assert_eq!(&open.data[..], &[0, 2, 4, 2, 4, 6][..]);
+    }
+
+    #[test]
+    fn reachable_test() {
+        let (grid, _) = super::super::parse_ascii(
+            "
++-+-+-+
+| |   |
++-+-+ +
+|     |
++-+-+-+
+",
+        )
+        .unwrap();
+        let reachable = grid.reachable((1, 0));
+        assert_eq!(
+            &reachable.data[..],
+            &[false, true, true, true, true, true][..]
+        );
+        let distances = grid.distances(&[(1, 0)]);
+        assert_eq!(distances.data[0], UNREACHABLE);
+        assert_eq!(grid.neighbors((0, 0)).count(), 2);
+        assert_eq!(grid.open_neighbors((0, 0)).count(), 0);
+        assert_eq!(grid.open_neighbors((1, 1)).count(), 2);
+    }
+}
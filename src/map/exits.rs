@@ -0,0 +1,89 @@
+use super::{bfs, neighbor_coord, Coord, Direction, Graph};
+use rand::seq::SliceRandom;
+
+/// A dead end that can be walled off from the rest of the maze for a hidden exit.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Pocket {
+    /// The dead end itself, where the exit goes.
+    pub exit: Coord,
+    /// The wall to shut, out of the cell next to `exit`.
+    pub wall: (Coord, Direction),
+}
+
+/// Looks for a dead end at the end of a corridor at least two cells long. Shutting the wall
+/// behind the corridor leaves two cells joined to each other and nothing else, so they're
+/// still part of the maze for `NoClip` to get into. Nothing in `avoid` gets walled off.
+pub fn find_pocket<R: rand::Rng>(graph: &Graph, avoid: &[Coord], rng: &mut R) -> Option<Pocket> {
+    let degree = |node: Coord| graph.neighbors(node).count();
+    let mut pockets = graph
+        .nodes()
+        .filter(|node| degree(*node) == 1 && !avoid.contains(node))
+        .filter_map(|exit| {
+            let corridor = graph.neighbors(exit).next()?;
+            if degree(corridor) != 2 || avoid.contains(&corridor) {
+                return None;
+            }
+            let behind = graph.neighbors(corridor).find(|node| *node != exit)?;
+            let direction = IntoIterator::into_iter(Direction::cardinals())
+                .find(|direction| neighbor_coord(corridor, *direction).ok() == Some(behind))?;
+            Some(Pocket {
+                exit,
+                wall: (corridor, direction),
+            })
+        })
+        .collect::<Vec<_>>();
+    pockets.sort_unstable_by_key(|pocket| pocket.exit);
+    pockets.choose(rng).copied()
+}
+
+/// The dead end furthest away from `from` that isn't in `avoid`.
+pub fn far_dead_end(graph: &Graph, from: Coord, avoid: &[Coord]) -> Option<Coord> {
+    bfs(graph, from)
+        .into_iter()
+        .filter(|(node, _)| graph.neighbors(*node).count() == 1 && !avoid.contains(node))
+        .map(|(node, (distance, _))| (distance, node))
+        .max()
+        .map(|(_, node)| node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::MAZE;
+    use super::*;
+
+    #[test]
+    fn find_pocket_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let graph = grid.as_graph();
+        let mut rng: rand::rngs::SmallRng = rand::SeedableRng::seed_from_u64(0);
+
+        // (0, 0) is a dead end straight off of the junction so it can't be walled off
+        let pocket = find_pocket(&graph, &[], &mut rng).unwrap();
+        assert!(pocket.exit == (0, 1) || pocket.exit == (2, 1));
+        let pocket = find_pocket(&graph, &[(0, 1)], &mut rng).unwrap();
+        assert_eq!(
+            pocket,
+            Pocket {
+                exit: (2, 1),
+                wall: ((3, 1), Direction::N),
+            }
+        );
+        assert_eq!(find_pocket(&graph, &[(0, 1), (3, 1)], &mut rng), None);
+    }
+
+    #[test]
+    fn far_dead_end_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let graph = grid.as_graph();
+        assert_eq!(far_dead_end(&graph, (0, 0), &[]), Some((2, 1)));
+        assert_eq!(far_dead_end(&graph, (0, 0), &[(2, 1)]), Some((0, 1)));
+        assert_eq!(
+            far_dead_end(&graph, (0, 0), &[(2, 1), (0, 1)]),
+            Some((0, 0))
+        );
+        assert_eq!(
+            far_dead_end(&graph, (0, 0), &[(2, 1), (0, 1), (0, 0)]),
+            None
+        );
+    }
+}
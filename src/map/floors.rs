@@ -0,0 +1,88 @@
+use super::{Coord, DirectionGrid, Graph};
+use rand::seq::SliceRandom;
+
+/// A cell on a specific floor of a multi-floor level.
+pub type FloorCoord = (usize, Coord);
+pub type LayeredGraph = petgraph::graphmap::UnGraphMap<FloorCoord, ()>;
+
+/// Picks where each floor connects to the one above it. Ports stack vertically so
+/// `ports[i]` is the same cell on floors `i` and `i + 1`, and it's never void on either.
+pub fn place_ports<R: rand::Rng>(floors: &[&DirectionGrid], rng: &mut R) -> Vec<Coord> {
+    let mut ports: Vec<Coord> = Vec::with_capacity(floors.len().saturating_sub(1));
+    for pair in floors.windows(2) {
+        let (lower, upper) = (pair[0], pair[1]);
+        let candidates = lower
+            .iter()
+            .map(|(_, coord)| coord)
+            .filter(|coord| !lower.is_void(*coord) && !upper.is_void(*coord))
+            .collect::<Vec<_>>();
+        // prefer not to stack ports on top of each other but it's better than no port at all
+        let unused = candidates
+            .iter()
+            .copied()
+            .filter(|coord| ports.last() != Some(coord))
+            .collect::<Vec<_>>();
+        let port = unused
+            .choose(rng)
+            .or_else(|| candidates.choose(rng))
+            .copied()
+            .unwrap_or((0, 0));
+        ports.push(port);
+    }
+    ports
+}
+
+/// Joins the graphs of every floor into one, linking floors through their ports.
+pub fn layered_graph(floors: &[Graph], ports: &[Coord]) -> LayeredGraph {
+    let nodes = floors.iter().map(Graph::node_count).sum();
+    let edges = floors.iter().map(Graph::edge_count).sum::<usize>() + ports.len();
+    let mut graph = LayeredGraph::with_capacity(nodes, edges);
+
+    for (floor, inner) in floors.iter().enumerate() {
+        for node in inner.nodes() {
+            graph.add_node((floor, node));
+        }
+        for (a, b, _) in inner.all_edges() {
+            graph.add_edge((floor, a), (floor, b), ());
+        }
+    }
+    for (floor, port) in ports.iter().copied().enumerate() {
+        if graph.contains_node((floor, port)) && graph.contains_node((floor + 1, port)) {
+            graph.add_edge((floor, port), (floor + 1, port), ());
+        }
+    }
+
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::{Generator, MazeGenerator};
+    use rand::SeedableRng;
+
+    #[test]
+    fn layered_graph_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let grids = (0..3)
+            .map(|_| Generator::Kruskal.generate(6, 6, &mut rng))
+            .collect::<Vec<_>>();
+        let ports = place_ports(&grids.iter().collect::<Vec<_>>(), &mut rng);
+        assert_eq!(ports.len(), 2);
+
+        let graphs = grids
+            .iter()
+            .map(DirectionGrid::as_graph)
+            .collect::<Vec<_>>();
+        let graph = layered_graph(&graphs, &ports);
+        assert_eq!(graph.node_count(), 6 * 6 * 3);
+        // three perfect mazes plus the two ports is still a tree
+        assert_eq!(graph.edge_count(), graph.node_count() - 1);
+
+        let path = crate::map::longest_path(&graph);
+        let (start, end) = (path[0], path[path.len() - 1]);
+        let distances = petgraph::algo::dijkstra(&graph, start, None, |_| 1usize);
+        assert_eq!(distances.len(), graph.node_count());
+        assert_eq!(distances.values().max(), distances.get(&end));
+    }
+}
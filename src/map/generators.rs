@@ -0,0 +1,413 @@
+use super::{Coord, Direction, DirectionGrid, Grid, Weights};
+use enumflags2::BitFlags;
+use rand::seq::SliceRandom;
+
+pub trait MazeGenerator {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Generator {
+    GrowingTree(Weights),
+    RecursiveBacktracker,
+    Kruskal,
+    Prim,
+    Wilson,
+    Eller,
+    BinaryTree,
+    Sidewinder,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::GrowingTree(Weights::default())
+    }
+}
+
+impl MazeGenerator for Generator {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        match self {
+            Generator::GrowingTree(weights) => {
+                GrowingTree { weights: *weights }.generate(width, height, rng)
+            }
+            Generator::RecursiveBacktracker => RecursiveBacktracker.generate(width, height, rng),
+            Generator::Kruskal => Kruskal.generate(width, height, rng),
+            Generator::Prim => Prim.generate(width, height, rng),
+            Generator::Wilson => Wilson.generate(width, height, rng),
+            Generator::Eller => Eller.generate(width, height, rng),
+            Generator::BinaryTree => BinaryTree.generate(width, height, rng),
+            Generator::Sidewinder => Sidewinder.generate(width, height, rng),
+        }
+    }
+}
+
+fn random_coord<R: rand::Rng>(width: usize, height: usize, rng: &mut R) -> Coord {
+    (rng.gen_range(0..width), rng.gen_range(0..height))
+}
+
+/// The growing tree family. Depending on the `Weights` this can behave like a
+/// recursive backtracker, Prim's, or anything in between.
+pub struct GrowingTree {
+    pub weights: Weights,
+}
+
+impl MazeGenerator for GrowingTree {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let grid = super::growing_tree(width, height, self.weights, rng);
+        Grid {
+            data: grid.into_boxed_slice(),
+            width,
+            height,
+        }
+    }
+}
+
+/// Depth first search with an explicit stack. Produces long, winding corridors
+/// with comparatively few dead ends.
+pub struct RecursiveBacktracker;
+
+impl MazeGenerator for RecursiveBacktracker {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        let mut visited = Grid::filled(width, height, false);
+
+        let start = random_coord(width, height, rng);
+        visited.data[visited.coord_to_index(start)] = true;
+        let mut stack = vec![start];
+
+        let mut directions = Direction::cardinals();
+        while let Some(current) = stack.last().copied() {
+            directions.shuffle(rng);
+            let next = directions.iter().copied().find_map(|direction| {
+                let next = visited.neighbor(current, direction)?;
+                if visited.data[visited.coord_to_index(next)] {
+                    None
+                } else {
+                    Some((direction, next))
+                }
+            });
+            match next {
+                Some((direction, next)) => {
+                    grid.make_open(current, direction);
+                    visited.data[visited.coord_to_index(next)] = true;
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        grid
+    }
+}
+
+/// Randomized Kruskal's: joins cells along shuffled walls whenever the two sides
+/// belong to different trees. Lots of short dead ends.
+pub struct Kruskal;
+
+impl MazeGenerator for Kruskal {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        let mut sets = DisjointSet::new(width * height);
+
+        let mut edges = Vec::with_capacity(width * height * 2);
+        for (_, coord) in grid.iter() {
+            for direction in [Direction::E, Direction::S].iter().copied() {
+                if grid.neighbor(coord, direction).is_some() {
+                    edges.push((coord, direction));
+                }
+            }
+        }
+        edges.shuffle(rng);
+
+        for (coord, direction) in edges {
+            if let Some(next) = grid.neighbor(coord, direction) {
+                let a = grid.coord_to_index(coord);
+                let b = grid.coord_to_index(next);
+                if sets.union(a, b) {
+                    grid.make_open(coord, direction);
+                }
+            }
+        }
+
+        grid
+    }
+}
+
+/// Randomized Prim's: grows a single tree from a random frontier wall.
+/// Radiates out from the start cell with many short branches.
+pub struct Prim;
+
+impl MazeGenerator for Prim {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        let mut in_tree = Grid::filled(width, height, false);
+
+        fn add(coord: Coord, in_tree: &mut Grid<bool>, frontier: &mut Vec<(Coord, Direction)>) {
+            let index = in_tree.coord_to_index(coord);
+            in_tree.data[index] = true;
+            for direction in Direction::cardinals().iter().copied() {
+                if let Some(next) = in_tree.neighbor(coord, direction) {
+                    if !in_tree.data[in_tree.coord_to_index(next)] {
+                        frontier.push((coord, direction));
+                    }
+                }
+            }
+        }
+
+        let mut frontier = Vec::new();
+
+        add(
+            random_coord(width, height, rng),
+            &mut in_tree,
+            &mut frontier,
+        );
+        while !frontier.is_empty() {
+            let (coord, direction) = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+            if let Some(next) = in_tree.neighbor(coord, direction) {
+                if !in_tree.data[in_tree.coord_to_index(next)] {
+                    grid.make_open(coord, direction);
+                    add(next, &mut in_tree, &mut frontier);
+                }
+            }
+        }
+
+        grid
+    }
+}
+
+/// Wilson's algorithm: loop-erased random walks. Samples uniformly from all
+/// possible spanning trees so it has no directional bias at all.
+pub struct Wilson;
+
+impl MazeGenerator for Wilson {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        let mut in_tree = Grid::filled(width, height, false);
+        // the last direction taken out of each cell during the current walk
+        let mut walk: Grid<Option<Direction>> = Grid::filled(width, height, None);
+
+        let root = random_coord(width, height, rng);
+        in_tree.data[in_tree.coord_to_index(root)] = true;
+
+        let mut order = (0..(width * height)).collect::<Vec<_>>();
+        order.shuffle(rng);
+
+        let directions = Direction::cardinals();
+        for start in order {
+            if in_tree.data[start] {
+                continue;
+            }
+
+            let mut current = in_tree.index_to_coord(start);
+            while !in_tree.data[in_tree.coord_to_index(current)] {
+                let (direction, next) = loop {
+                    let direction = *directions.choose(rng).unwrap();
+                    if let Some(next) = in_tree.neighbor(current, direction) {
+                        break (direction, next);
+                    }
+                };
+                walk.data[walk.coord_to_index(current)] = Some(direction);
+                current = next;
+            }
+
+            let mut current = in_tree.index_to_coord(start);
+            while !in_tree.data[in_tree.coord_to_index(current)] {
+                let index = in_tree.coord_to_index(current);
+                in_tree.data[index] = true;
+                let direction = walk.data[index].take().unwrap();
+                grid.make_open(current, direction);
+                current = in_tree.neighbor(current, direction).unwrap();
+            }
+        }
+
+        grid
+    }
+}
+
+/// Eller's algorithm: builds the maze one row at a time, tracking which cells
+/// of the current row are already connected.
+pub struct Eller;
+
+impl MazeGenerator for Eller {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        let mut sets = vec![0usize; width];
+        let mut next_set = 1;
+
+        for y in 0..height {
+            let last_row = y + 1 == height;
+
+            for set in sets.iter_mut() {
+                if *set == 0 {
+                    *set = next_set;
+                    next_set += 1;
+                }
+            }
+
+            for x in 0..width.saturating_sub(1) {
+                let (a, b) = (sets[x], sets[x + 1]);
+                if a != b && (last_row || rng.gen_bool(0.5)) {
+                    grid.make_open((x, y), Direction::E);
+                    for set in sets.iter_mut() {
+                        if *set == b {
+                            *set = a;
+                        }
+                    }
+                }
+            }
+
+            if last_row {
+                break;
+            }
+
+            let mut groups = std::collections::BTreeMap::<usize, Vec<usize>>::new();
+            for (x, set) in sets.iter().copied().enumerate() {
+                groups.entry(set).or_default().push(x);
+            }
+
+            let mut next = vec![0usize; width];
+            for mut group in groups.into_values() {
+                group.shuffle(rng);
+                let count = rng.gen_range(1..=group.len());
+                for x in group.into_iter().take(count) {
+                    grid.make_open((x, y), Direction::S);
+                    next[x] = sets[x];
+                }
+            }
+            sets = next;
+        }
+
+        grid
+    }
+}
+
+/// Each cell opens either north or west. Trivially simple with a strong
+/// diagonal bias and two unbroken corridors along the top and left edges.
+pub struct BinaryTree;
+
+impl MazeGenerator for BinaryTree {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        for index in 0..grid.data.len() {
+            let coord = grid.index_to_coord(index);
+            let choices = [Direction::N, Direction::W]
+                .iter()
+                .copied()
+                .filter(|direction| grid.neighbor(coord, *direction).is_some())
+                .collect::<Vec<_>>();
+            if let Some(direction) = choices.choose(rng) {
+                grid.make_open(coord, *direction);
+            }
+        }
+        grid
+    }
+}
+
+/// Binary tree's row based cousin: carves runs eastward and closes each run
+/// with a single opening to the north. Only the top row is an unbroken corridor.
+pub struct Sidewinder;
+
+impl MazeGenerator for Sidewinder {
+    fn generate<R: rand::Rng>(&self, width: usize, height: usize, rng: &mut R) -> DirectionGrid {
+        let mut grid = DirectionGrid::filled(width, height, BitFlags::empty());
+        for y in 0..height {
+            let mut run_start = 0;
+            for x in 0..width {
+                let at_east = x + 1 == width;
+                let at_north = y == 0;
+                if at_east || (!at_north && rng.gen_bool(0.5)) {
+                    if !at_north {
+                        let cell = rng.gen_range(run_start..=x);
+                        grid.make_open((cell, y), Direction::N);
+                    }
+                    run_start = x + 1;
+                } else {
+                    grid.make_open((x, y), Direction::E);
+                }
+            }
+        }
+        grid
+    }
+}
+
+pub(super) struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub(super) fn new(count: usize) -> Self {
+        Self {
+            parents: (0..count).collect(),
+        }
+    }
+
+    pub(super) fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    /// Returns false if the two were already in the same set.
+    pub(super) fn union(&mut self, a: usize, b: usize) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            false
+        } else {
+            self.parents[b] = a;
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Generator; 8] = [
+        Generator::GrowingTree(Weights::NONE),
+        Generator::RecursiveBacktracker,
+        Generator::Kruskal,
+        Generator::Prim,
+        Generator::Wilson,
+        Generator::Eller,
+        Generator::BinaryTree,
+        Generator::Sidewinder,
+    ];
+
+    #[test]
+    fn perfect_maze_test() {
+        use rand::SeedableRng;
+        for generator in ALL.iter() {
+            for seed in 0..4 {
+                let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+                let (width, height) = (7, 5);
+                let grid = generator.generate(width, height, &mut rng);
+                assert_eq!(grid.grid_size(), [width, height]);
+
+                // a spanning tree touches every node with exactly n - 1 edges
+                let graph = grid.as_graph();
+                assert_eq!(graph.node_count(), width * height, "{:?}", generator);
+                let openings = grid
+                    .data
+                    .iter()
+                    .map(|cell| cell.bits().count_ones())
+                    .sum::<u32>();
+                assert_eq!(openings as usize / 2, width * height - 1, "{:?}", generator);
+
+                for (cell, coord) in grid.iter() {
+                    for direction in cell.iter() {
+                        let next = grid.neighbor(coord, direction);
+                        assert!(next.is_some(), "{:?} opens out of bounds", generator);
+                        let next = grid.data[grid.coord_to_index(next.unwrap())];
+                        assert!(next.contains(direction.opposite()), "{:?}", generator);
+                    }
+                }
+            }
+        }
+    }
+}
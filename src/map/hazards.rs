@@ -0,0 +1,124 @@
+use super::{Coord, Graph, Grid};
+use rand::seq::SliceRandom;
+
+/// What's on the floor of a cell, on top of whatever walls it has.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Terrain {
+    #[default]
+    Clear,
+    /// Burns a program to cross and kills without one.
+    Firewall,
+    /// Sets every enemy on the floor after whoever steps on it.
+    Scanner,
+    /// Takes longer to walk into.
+    Slow,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct HazardSettings {
+    pub firewalls: usize,
+    pub scanners: usize,
+    pub slow: usize,
+}
+
+/// Scatters the hazards of `settings` over `graph`, never on one of `exclude`. Firewalls
+/// only go in dead ends off of `path` so that they never stand in the way of anything but
+/// themselves, slow cells go on the path first and scanners go anywhere.
+pub fn place_hazards<R: rand::Rng>(
+    graph: &Graph,
+    path: &[Coord],
+    settings: HazardSettings,
+    exclude: &[Coord],
+    size: [usize; 2],
+    rng: &mut R,
+) -> Grid<Terrain> {
+    let [width, height] = size;
+    let mut terrain = Grid::filled(width, height, Terrain::Clear);
+    let mut nodes = graph
+        .nodes()
+        .filter(|node| !exclude.contains(node) && terrain.contains(*node))
+        .collect::<Vec<_>>();
+    nodes.sort_unstable();
+
+    let (mut on_path, off_path): (Vec<_>, Vec<_>) =
+        nodes.into_iter().partition(|node| path.contains(node));
+    let (mut dead_ends, mut off_path): (Vec<_>, Vec<_>) = off_path
+        .into_iter()
+        .partition(|node| graph.neighbors(*node).count() == 1);
+    on_path.shuffle(rng);
+    dead_ends.shuffle(rng);
+
+    // returns how many were placed
+    let mut place = |cells: &mut Vec<Coord>, count: usize, kind: Terrain| {
+        let count = count.min(cells.len());
+        for coord in cells.drain(..count) {
+            let index = terrain.coord_to_index(coord);
+            terrain.data[index] = kind;
+        }
+        count
+    };
+    place(&mut dead_ends, settings.firewalls, Terrain::Firewall);
+    let slow = place(&mut on_path, settings.slow, Terrain::Slow);
+    off_path.append(&mut dead_ends);
+    let mut rest = on_path.into_iter().chain(off_path).collect::<Vec<_>>();
+    rest.sort_unstable();
+    rest.shuffle(rng);
+    place(&mut rest, settings.slow - slow, Terrain::Slow);
+    place(&mut rest, settings.scanners, Terrain::Scanner);
+    terrain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    /// A corridor along the top with a dead end straight off of nearly every cell of it,
+    /// and a void cell where the last one would be.
+    const COMB: &str = "
++-+-+-+-+
+|       |
++ +-+ + +
+| | | | |
++-+-+-+-+
+";
+
+    #[test]
+    fn place_hazards_test() {
+        let (grid, _) = super::super::parse_ascii(COMB).unwrap();
+        let graph = grid.as_graph();
+        let path = [(0, 0), (1, 0), (2, 0), (3, 0)];
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let settings = HazardSettings {
+            firewalls: 5,
+            scanners: 1,
+            slow: 2,
+        };
+
+        let terrain = place_hazards(&graph, &path, settings, &[(2, 1)], [4, 2], &mut rng);
+        let at = |coord: Coord| terrain.data[terrain.coord_to_index(coord)];
+        let count = |kind: Terrain| terrain.data.iter().filter(|t| **t == kind).count();
+        // right up against the path but never on it, and never on what's excluded
+        assert_eq!(count(Terrain::Firewall), 2);
+        assert_eq!(at((0, 1)), Terrain::Firewall);
+        assert_eq!(at((3, 1)), Terrain::Firewall);
+        assert_eq!(at((2, 1)), Terrain::Clear);
+        assert_eq!(count(Terrain::Slow), 2);
+        assert_eq!(count(Terrain::Scanner), 1);
+        for (kind, coord) in terrain.iter() {
+            if *kind == Terrain::Slow || *kind == Terrain::Scanner {
+                assert!(path.contains(&coord));
+            }
+        }
+        assert_eq!(at((1, 1)), Terrain::Clear);
+
+        // more slow cells than the path has spill over onto everything else
+        let settings = HazardSettings {
+            slow: 10,
+            ..Default::default()
+        };
+        let terrain = place_hazards(&graph, &path, settings, &[], [4, 2], &mut rng);
+        let slow = terrain.data.iter().filter(|t| **t == Terrain::Slow).count();
+        assert_eq!(slow, 7);
+    }
+}
@@ -0,0 +1,237 @@
+use super::{Coord, Direction, DirectionGrid, Grid};
+use crate::programs::Program;
+use enumflags2::BitFlags;
+
+/// A program lying on the map, waiting to be picked up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Pickup {
+    pub coord: Coord,
+    pub program: Program,
+}
+
+/// A hand-made level. Can be written as JSON or drawn as ASCII art, see `Level::from_ascii`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Level {
+    pub width: usize,
+    pub height: usize,
+    /// The open sides of every cell, row by row, as `Direction` bits.
+    /// Cells without any open sides are void.
+    pub cells: Vec<u8>,
+    pub start: Coord,
+    pub exit: Coord,
+    #[serde(default)]
+    pub enemies: Vec<Coord>,
+    #[serde(default)]
+    pub pickups: Vec<Pickup>,
+}
+
+impl Level {
+    pub fn from_json(data: &str) -> eyre::Result<Self> {
+        let level: Self = serde_json::from_str(data)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Parses a level drawn like this:
+    ///
+    /// ```text
+    /// +-+-+-+
+    /// |@  |X|
+    /// + +-+ +
+    /// |E   N|
+    /// +-+-+-+
+    /// ```
+    ///
+    /// Cells sit between the `+` corners with their walls drawn around them; a space in a
+    /// wall's spot leaves it open. Inside a cell `@` marks the start, `X` the exit,
+    /// `E` an enemy, `N` a NOP slide and `C` a clip. Blank lines around the level are ignored.
+    pub fn from_ascii(template: &str) -> eyre::Result<Self> {
+        let (mut grid, markers) = super::parse_ascii(template)?;
+        let mut start = None;
+        let mut exit = None;
+        let mut enemies = Vec::new();
+        let mut pickups = Vec::new();
+
+        for (marker, coord) in markers.iter() {
+            match marker {
+                ' ' => {}
+                '@' => start = Some(coord),
+                'X' => exit = Some(coord),
+                'E' => enemies.push(coord),
+                'N' => pickups.push(Pickup {
+                    coord,
+                    program: Program::NopSlide,
+                }),
+                'C' => pickups.push(Pickup {
+                    coord,
+                    program: Program::NoClip,
+                }),
+                c => {
+                    return Err(eyre::Report::msg(format!(
+                        "Unknown marker '{}' in level template at line {}, column {}.",
+                        c,
+                        coord.1 * 2 + 2,
+                        coord.0 * 2 + 2
+                    )))
+                }
+            }
+        }
+        super::apply_not_corner_bit(&mut grid);
+
+        let level = Self {
+            width: grid.width,
+            height: grid.height,
+            cells: grid.data.iter().map(|cell| cell.bits()).collect(),
+            start: start.ok_or_else(|| eyre::Report::msg("Level template has no start (@)."))?,
+            exit: exit.ok_or_else(|| eyre::Report::msg("Level template has no exit (X)."))?,
+            enemies,
+            pickups,
+        };
+        level.validate()?;
+        Ok(level)
+    }
+
+    pub fn grid(&self) -> eyre::Result<DirectionGrid> {
+        if self.cells.len() != self.width * self.height {
+            return Err(eyre::Report::msg(format!(
+                "Level has {} cells but should have {}.",
+                self.cells.len(),
+                self.width * self.height
+            )));
+        }
+        let data = self
+            .cells
+            .iter()
+            .map(|bits| {
+                BitFlags::from_bits(*bits)
+                    .map_err(|_| eyre::Report::msg(format!("Invalid cell bits {}.", bits)))
+            })
+            .collect::<eyre::Result<Box<[_]>>>()?;
+        Ok(Grid {
+            data,
+            width: self.width,
+            height: self.height,
+        })
+    }
+
+    /// Checks that the cells describe a sensible maze and that everything placed on it is
+    /// somewhere the player can actually get to.
+    pub fn validate(&self) -> eyre::Result<()> {
+        let grid = self.grid()?;
+        for (cell, coord) in grid.iter() {
+            if !super::tiles::has_valid_corners(*cell) {
+                return Err(eyre::Report::msg(format!(
+                    "Cell {:?} has a corner open without both sides next to it.",
+                    coord
+                )));
+            }
+            for direction in Direction::cardinals().iter().copied() {
+                if !cell.contains(direction) {
+                    continue;
+                }
+                let neighbor = grid.neighbor(coord, direction);
+                let matched = neighbor.is_some_and(|neighbor| {
+                    grid.data[grid.coord_to_index(neighbor)].contains(direction.opposite())
+                });
+                if !matched {
+                    return Err(eyre::Report::msg(format!(
+                        "Cell {:?} is open to the {:?} but the other side isn't.",
+                        coord, direction
+                    )));
+                }
+            }
+        }
+
+        if !grid.contains(self.start) {
+            return Err(eyre::Report::msg(format!(
+                "Level start {:?} is outside of the level.",
+                self.start
+            )));
+        }
+        let reached = grid.reachable(self.start);
+        let reachable = |coord: &Coord| {
+            reached
+                .checked_coord_to_index(*coord)
+                .is_some_and(|index| reached.data[index])
+        };
+        let placed = std::iter::once(&self.exit)
+            .chain(self.enemies.iter())
+            .chain(self.pickups.iter().map(|pickup| &pickup.coord));
+        for coord in placed {
+            if !reachable(coord) {
+                return Err(eyre::Report::msg(format!(
+                    "{:?} can't be reached from the start of the level.",
+                    coord
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "
++-+-+-+
+|@  |X|
++ +-+ +
+|E   N|
++-+-+-+
+";
+
+    #[test]
+    fn from_ascii_test() {
+        let level = Level::from_ascii(LEVEL).unwrap();
+        assert_eq!((level.width, level.height), (3, 2));
+        assert_eq!(level.start, (0, 0));
+        assert_eq!(level.exit, (2, 0));
+        assert_eq!(level.enemies, vec![(0, 1)]);
+        assert_eq!(
+            level.pickups,
+            vec![Pickup {
+                coord: (2, 1),
+                program: Program::NopSlide
+            }]
+        );
+
+        let grid = level.grid().unwrap();
+        assert_eq!(grid.valid_move((0, 0), Direction::E), Some((1, 0)));
+        assert_eq!(grid.valid_move((1, 0), Direction::E), None);
+        assert_eq!(grid.valid_move((1, 0), Direction::S), None);
+        assert_eq!(grid.valid_move((2, 1), Direction::N), Some((2, 0)));
+        assert_eq!(grid.as_graph().edge_count(), 5);
+    }
+
+    #[test]
+    fn tutorial_test() {
+        let level = Level::from_ascii(crate::TUTORIAL_LEVEL).unwrap();
+        assert_eq!(level.pickups.len(), 2);
+        assert!(level.enemies.is_empty());
+    }
+
+    #[test]
+    fn json_round_trip_test() {
+        let level = Level::from_ascii(LEVEL).unwrap();
+        let json = serde_json::to_string(&level).unwrap();
+        assert_eq!(Level::from_json(&json).unwrap(), level);
+    }
+
+    #[test]
+    fn invalid_level_test() {
+        // no exit
+        assert!(Level::from_ascii("+-+-+\n|@  |\n+-+-+").is_err());
+        // exit walled off from the start
+        assert!(Level::from_ascii("+-+-+\n|@|X|\n+-+-+").is_err());
+        // unknown marker
+        assert!(Level::from_ascii("+-+-+\n|@ ?|\n+-+-+").is_err());
+        // marker where a wall should be
+        assert!(Level::from_ascii("+-+-+\n|@X  |\n+-+-+").is_err());
+        // even sized
+        assert!(Level::from_ascii("+-+-\n|@ X\n+-+-").is_err());
+        // a corner open without the sides next to it
+        let json = r#"{ "width": 1, "height": 1, "cells": [16], "start": [0, 0], "exit": [0, 0] }"#;
+        assert!(Level::from_json(json).is_err());
+    }
+}
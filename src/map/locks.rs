@@ -0,0 +1,309 @@
+use super::{neighbor_coord, Coord, Direction, DirectionGrid, Graph};
+use rand::seq::SliceRandom;
+
+/// An open side of a cell that stays shut until its key is picked up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Door {
+    pub cell: Coord,
+    pub direction: Direction,
+    pub key: usize,
+}
+
+impl Door {
+    /// Whether stepping out of `from` towards `direction` goes through the door,
+    /// from either side of it.
+    pub fn blocks(&self, from: Coord, direction: Direction) -> bool {
+        let other = neighbor_coord(self.cell, self.direction).ok();
+        (self.cell == from && self.direction == direction)
+            || (other == Some(from) && self.direction.opposite() == direction)
+    }
+
+    fn between(&self, a: Coord, b: Coord) -> bool {
+        let other = neighbor_coord(self.cell, self.direction).ok();
+        (self.cell == a && other == Some(b)) || (self.cell == b && other == Some(a))
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Key {
+    pub coord: Coord,
+    pub key: usize,
+}
+
+/// The doors of a floor along with the keys that open them.
+#[derive(Debug, Clone, Default)]
+pub struct Locks {
+    pub doors: Vec<Door>,
+    pub keys: Vec<Key>,
+}
+
+impl DirectionGrid {
+    /// `valid_move` that also won't go through any door that's still locked.
+    pub fn valid_move_with(
+        &self,
+        start: Coord,
+        direction: Direction,
+        locks: &Locks,
+    ) -> Option<Coord> {
+        self.valid_move(start, direction)
+            .filter(|_| !locks.blocks(start, direction))
+    }
+}
+
+impl Locks {
+    pub fn blocks(&self, from: Coord, direction: Direction) -> bool {
+        self.doors.iter().any(|door| door.blocks(from, direction))
+    }
+
+    /// Picks up the key lying at `coord`, if there is one, which opens every door it fits.
+    pub fn take_key(&mut self, coord: Coord) -> Option<usize> {
+        let index = self.keys.iter().position(|key| key.coord == coord)?;
+        let key = self.keys.remove(index).key;
+        self.doors.retain(|door| door.key != key);
+        Some(key)
+    }
+
+    /// Puts up to `count` doors along `path`, each with its key somewhere that can be
+    /// reached from the start of the path without going through that door or any door
+    /// after it. Keys prefer dead ends off of the path so that finding them means exploring,
+    /// but never go in `hidden`, where nothing would let on that they're there.
+    pub fn generate<R: rand::Rng>(
+        graph: &Graph,
+        path: &[Coord],
+        count: usize,
+        hidden: &[Coord],
+        rng: &mut R,
+    ) -> Self {
+        let mut locks = Self::default();
+        let start = match path.first() {
+            Some(start) if path.len() > 2 => *start,
+            _ => return locks,
+        };
+
+        // never right at the start, where there'd be nowhere to hide the key
+        let mut edges =
+            rand::seq::index::sample(rng, path.len() - 2, count.min(path.len() - 2)).into_vec();
+        edges.sort_unstable();
+        let doors = edges
+            .into_iter()
+            .enumerate()
+            .filter_map(|(key, edge)| {
+                let (from, to) = (path[edge + 1], path[edge + 2]);
+                let direction = IntoIterator::into_iter(Direction::cardinals())
+                    .find(|direction| neighbor_coord(from, *direction).ok() == Some(to))?;
+                Some(Door {
+                    cell: from,
+                    direction,
+                    key,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (index, door) in doors.iter().enumerate() {
+            // everything behind this door and the ones after it is out of reach
+            let region = reach(graph, start, &doors[index..]);
+            let taken = |coord: &Coord| {
+                *coord == start
+                    || hidden.contains(coord)
+                    || locks.keys.iter().any(|key| key.coord == *coord)
+            };
+            let off_path = |coord: &Coord| !path.contains(coord);
+            let dead_end = |coord: &Coord| graph.neighbors(*coord).count() == 1;
+
+            let candidates = [
+                region
+                    .iter()
+                    .filter(|c| !taken(c) && off_path(c) && dead_end(c))
+                    .copied()
+                    .collect::<Vec<_>>(),
+                region
+                    .iter()
+                    .filter(|c| !taken(c) && off_path(c))
+                    .copied()
+                    .collect(),
+                region.iter().filter(|c| !taken(c)).copied().collect(),
+            ];
+            let coord = candidates
+                .iter()
+                .find(|candidates| !candidates.is_empty())
+                .and_then(|candidates| candidates.choose(rng));
+            if let Some(coord) = coord {
+                locks.doors.push(*door);
+                locks.keys.push(Key {
+                    coord: *coord,
+                    key: door.key,
+                });
+            }
+        }
+        locks
+    }
+
+    /// Whether `to` can be reached from `from`, picking up keys along the way.
+    pub fn solvable(&self, graph: &Graph, from: Coord, to: Coord) -> bool {
+        let mut locks = self.clone();
+        loop {
+            let region = reach(graph, from, &locks.doors);
+            if region.contains(&to) {
+                return true;
+            }
+            let found = region
+                .iter()
+                .filter_map(|coord| locks.take_key(*coord))
+                .count();
+            if found == 0 {
+                return false;
+            }
+        }
+    }
+}
+
+/// Every node that can be reached from `from` without going through `doors`, sorted so
+/// that choosing out of them doesn't depend on the order of a hash map.
+fn reach(graph: &Graph, from: Coord, doors: &[Door]) -> Vec<Coord> {
+    let mut found = std::collections::HashSet::new();
+    let mut open = vec![from];
+    found.insert(from);
+    while let Some(node) = open.pop() {
+        for next in graph.neighbors(node) {
+            let locked = doors.iter().any(|door| door.between(node, next));
+            if !locked && found.insert(next) {
+                open.push(next);
+            }
+        }
+    }
+    let mut found = found.into_iter().collect::<Vec<_>>();
+    found.sort_unstable();
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn door_test() {
+        let door = Door {
+            cell: (1, 1),
+            direction: Direction::E,
+            key: 0,
+        };
+        assert!(door.blocks((1, 1), Direction::E));
+        assert!(door.blocks((2, 1), Direction::W));
+        assert!(!door.blocks((1, 1), Direction::W));
+        assert!(!door.blocks((2, 1), Direction::E));
+
+        let mut locks = Locks {
+            doors: vec![door],
+            keys: vec![Key {
+                coord: (0, 0),
+                key: 0,
+            }],
+        };
+        let (grid, _) = super::super::parse_ascii(
+            "
++-+-+-+
+|     |
++ + + +
+|     |
++-+-+-+
+",
+        )
+        .unwrap();
+        assert_eq!(grid.valid_move_with((1, 1), Direction::E, &locks), None);
+        assert_eq!(locks.take_key((1, 0)), None);
+        assert_eq!(locks.take_key((0, 0)), Some(0));
+        assert_eq!(
+            grid.valid_move_with((1, 1), Direction::E, &locks),
+            Some((2, 1))
+        );
+    }
+
+    #[test]
+    fn generate_test() {
+        let settings = super::super::MapGenSettings {
+            width: 12,
+            height: 12,
+            mask: None,
+            seed: None,
+            floors: 1,
+            generator: super::super::Generator::RecursiveBacktracker,
+            braid: super::super::BraidSettings {
+                dead_end_ratio: 0.25,
+                extra_loops: 0,
+            },
+            rooms: Default::default(),
+            programs: Default::default(),
+            enemies: Default::default(),
+            difficulty: Default::default(),
+            sight: 2,
+            doors: 3,
+            secrets: 0,
+            shift_every: None,
+            hazards: Default::default(),
+            tileset: super::super::DEFAULT_TILESET,
+            aesthetic: Default::default(),
+        };
+        for seed in 0..20 {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+            let (grid, _) = settings.generate(&mut rng);
+            let graph = grid.as_graph();
+            let path = super::super::longest_path(&graph);
+            let locks = Locks::generate(&graph, &path, settings.doors, &[], &mut rng);
+            assert_eq!(locks.doors.len(), locks.keys.len());
+            assert!(!locks.doors.is_empty());
+
+            let (start, exit) = (path[0], *path.last().unwrap());
+            assert!(locks.solvable(&graph, start, exit));
+            // every key is on the near side of its own door
+            for key in locks.keys.iter() {
+                let door = locks.doors.iter().position(|door| door.key == key.key);
+                let later = &locks.doors[door.unwrap()..];
+                assert!(reach(&graph, start, later).contains(&key.coord));
+            }
+        }
+    }
+
+    #[test]
+    fn hidden_test() {
+        let mut settings = super::super::MapGenSettings {
+            width: 12,
+            height: 12,
+            mask: None,
+            seed: None,
+            floors: 1,
+            generator: super::super::Generator::RecursiveBacktracker,
+            braid: Default::default(),
+            rooms: Default::default(),
+            programs: Default::default(),
+            enemies: Default::default(),
+            difficulty: Default::default(),
+            sight: 2,
+            doors: 3,
+            secrets: 4,
+            shift_every: None,
+            hazards: Default::default(),
+            tileset: super::super::DEFAULT_TILESET,
+            aesthetic: Default::default(),
+        };
+        settings.braid.dead_end_ratio = 0.;
+        for seed in 0..20 {
+            let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+            let (grid, _) = settings.generate(&mut rng);
+            let graph = grid.as_graph();
+            let path = super::super::longest_path(&graph);
+            let secrets =
+                super::super::Secrets::generate(&grid, &graph, &path, settings.secrets, &mut rng);
+            let hidden = secrets.behind(&graph, path[0]);
+            assert!(!hidden.is_empty());
+
+            // the dead ends keys like best are just where secrets go
+            let locks = Locks::generate(&graph, &path, settings.doors, &hidden, &mut rng);
+            assert!(!locks.keys.is_empty());
+            for key in locks.keys.iter() {
+                assert!(!hidden.contains(&key.coord), "{:?}", key);
+            }
+            assert!(locks.solvable(&graph, path[0], *path.last().unwrap()));
+        }
+    }
+}
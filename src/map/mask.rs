@@ -0,0 +1,205 @@
+use super::{generators::DisjointSet, Direction, DirectionGrid, Grid};
+use enumflags2::BitFlags;
+use rand::seq::SliceRandom;
+
+/// Which cells of a map can be walked on. Masked out cells are "void": they stay
+/// closed on every side, are never generated into and render as empty space.
+pub type Mask = Grid<bool>;
+
+impl Mask {
+    /// Builds a mask from a template where `.` and spaces are void and anything else is usable.
+    /// Short lines are padded out with void.
+    pub fn from_ascii(template: &str) -> eyre::Result<Self> {
+        let lines = template
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let width = lines.iter().map(|line| line.chars().count()).max();
+        let width = width.ok_or_else(|| eyre::Report::msg("Mask template is empty."))?;
+        let height = lines.len();
+
+        let mut mask = Grid::filled(width, height, false);
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                let index = mask.coord_to_index((x, y));
+                mask.data[index] = !matches!(c, '.' | ' ');
+            }
+        }
+
+        match mask.data.iter().filter(|usable| **usable).count() {
+            0 => Err(eyre::Report::msg("Mask template has no usable cells.")),
+            1 => Err(eyre::Report::msg(
+                "Mask template needs more than one usable cell.",
+            )),
+            // every part of the maze has to be reachable from everywhere else
+            usable if mask.joined_up() != usable => Err(eyre::Report::msg(
+                "Mask template's usable cells aren't all joined up.",
+            )),
+            _ => Ok(mask),
+        }
+    }
+
+    /// How many usable cells can be reached from the first one without crossing the void.
+    fn joined_up(&self) -> usize {
+        let first = match self.data.iter().position(|usable| *usable) {
+            Some(first) => self.index_to_coord(first),
+            None => return 0,
+        };
+        let mut reached = Grid::filled(self.width, self.height, false);
+        let mut open = vec![first];
+        let mut count = 0;
+        while let Some(coord) = open.pop() {
+            let index = self.coord_to_index(coord);
+            if reached.data[index] {
+                continue;
+            }
+            reached.data[index] = true;
+            count += 1;
+            for direction in Direction::cardinals().iter().copied() {
+                if let Some(next) = self.neighbor(coord, direction) {
+                    let next_index = self.coord_to_index(next);
+                    if self.data[next_index] && !reached.data[next_index] {
+                        open.push(next);
+                    }
+                }
+            }
+        }
+        count
+    }
+}
+
+/// Seals off the void cells of a generated maze and reconnects whatever the mask cut apart.
+/// The result is still a perfect maze over every usable cell, which `Mask::from_ascii`
+/// makes sure are all joined up.
+pub fn apply_mask<R: rand::Rng>(grid: &mut DirectionGrid, mask: &Mask, rng: &mut R) {
+    debug_assert_eq!(grid.grid_size(), mask.grid_size());
+
+    for index in 0..grid.data.len() {
+        if !mask.data[index] {
+            grid.data[index] = BitFlags::empty();
+            continue;
+        }
+
+        let coord = grid.index_to_coord(index);
+        for direction in Direction::cardinals().iter().copied() {
+            let void = grid
+                .neighbor(coord, direction)
+                .is_none_or(|neighbor| !mask.data[mask.coord_to_index(neighbor)]);
+            if void {
+                grid.data[index].remove(direction);
+            }
+        }
+    }
+
+    let mut sets = DisjointSet::new(grid.data.len());
+    for index in 0..grid.data.len() {
+        let coord = grid.index_to_coord(index);
+        for direction in [Direction::E, Direction::S].iter().copied() {
+            if let Some(neighbor) = grid.valid_move(coord, direction) {
+                sets.union(index, grid.coord_to_index(neighbor));
+            }
+        }
+    }
+
+    let mut walls = Vec::new();
+    for index in 0..grid.data.len() {
+        let coord = grid.index_to_coord(index);
+        for direction in [Direction::E, Direction::S].iter().copied() {
+            if let Some(neighbor) = grid.neighbor(coord, direction) {
+                if mask.data[index] && mask.data[mask.coord_to_index(neighbor)] {
+                    walls.push((coord, direction, neighbor));
+                }
+            }
+        }
+    }
+    walls.shuffle(rng);
+
+    for (coord, direction, neighbor) in walls {
+        if sets.union(grid.coord_to_index(coord), grid.coord_to_index(neighbor)) {
+            grid.make_open(coord, direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::MazeGenerator;
+    use rand::SeedableRng;
+
+    const TEMPLATE: &str = "
+..####..
+.######.
+##.##.##
+########
+.#.#.#..
+";
+
+    #[test]
+    fn from_ascii_test() {
+        let mask = Mask::from_ascii(TEMPLATE).unwrap();
+        assert_eq!(mask.grid_size(), [8, 5]);
+        assert!(!mask.data[mask.coord_to_index((0, 0))]);
+        assert!(mask.data[mask.coord_to_index((2, 0))]);
+        assert!(!mask.data[mask.coord_to_index((7, 4))]);
+
+        assert!(Mask::from_ascii("\n\n").is_err());
+        assert!(Mask::from_ascii("...").is_err());
+        assert!(Mask::from_ascii(".#.").is_err());
+    }
+
+    #[test]
+    fn regions_test() {
+        // two letters side by side with nothing between them
+        let two = "
+###.#.#
+#...#.#
+###.###
+..#...#
+###...#
+";
+        assert!(Mask::from_ascii(two).is_err());
+        // touching at a corner isn't enough either
+        assert!(Mask::from_ascii("##..\n##..\n..##\n..##").is_err());
+
+        let joined = "
+###.#.#
+#...#.#
+#######
+..#...#
+###...#
+";
+        let mask = Mask::from_ascii(joined).unwrap();
+        assert_eq!(
+            mask.joined_up(),
+            mask.data.iter().filter(|usable| **usable).count()
+        );
+    }
+
+    #[test]
+    fn apply_mask_test() {
+        let mask = Mask::from_ascii(TEMPLATE).unwrap();
+        let usable = mask.data.iter().filter(|usable| **usable).count();
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for generator in [
+            crate::map::Generator::Eller,
+            crate::map::Generator::BinaryTree,
+        ]
+        .iter()
+        {
+            let mut grid = generator.generate(mask.width, mask.height, &mut rng);
+            apply_mask(&mut grid, &mask, &mut rng);
+
+            for (cell, coord) in grid.iter() {
+                let index = grid.coord_to_index(coord);
+                assert_eq!(cell.is_empty(), !mask.data[index], "{:?}", coord);
+            }
+
+            let graph = grid.as_graph();
+            assert_eq!(graph.node_count(), usable);
+            assert_eq!(graph.edge_count(), usable - 1);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::{path_distances, Coord, Graph, Pickup, ProgramGenSettings, Room, RoomTag};
+use crate::programs::Program;
+use rand::seq::SliceRandom;
+
+impl ProgramGenSettings {
+    /// Every program to put on the map, `nop_slide_count` NopSlides then `noclip_count` NoClips.
+    pub fn programs(&self) -> Vec<Program> {
+        std::iter::repeat_n(Program::NopSlide, self.nop_slide_count)
+            .chain(std::iter::repeat_n(Program::NoClip, self.noclip_count))
+            .collect()
+    }
+}
+
+/// Finds a cell for each of `programs`, never on one of `exclude`. Pickup rooms and the
+/// `hidden` cells behind secret passages are filled first, then dead ends off of `path`,
+/// then anything else off of it. Finding them should mean going somewhere the way to the
+/// exit doesn't.
+pub fn place_pickups<R: rand::Rng>(
+    graph: &Graph,
+    path: &[Coord],
+    rooms: &[Room],
+    hidden: &[Coord],
+    programs: &[Program],
+    exclude: &[Coord],
+    rng: &mut R,
+) -> Vec<Pickup> {
+    let mut nodes = graph
+        .nodes()
+        .filter(|node| !exclude.contains(node))
+        .collect::<Vec<_>>();
+    nodes.sort_unstable();
+    let distances = path_distances(graph, path, &nodes);
+
+    let in_pickup_room = |coord: Coord| {
+        rooms
+            .iter()
+            .any(|room| room.tag == RoomTag::Pickups && room.contains(coord))
+    };
+    let tier = |(coord, distance): (Coord, Option<usize>)| {
+        let off_path = distance.is_none_or(|distance| distance > 0);
+        if in_pickup_room(coord) || hidden.contains(&coord) {
+            0
+        } else if off_path && graph.neighbors(coord).count() == 1 {
+            1
+        } else if off_path {
+            2
+        } else {
+            3
+        }
+    };
+    let mut tiers = vec![vec![]; 4];
+    for (coord, distance) in nodes.iter().copied().zip(distances) {
+        tiers[tier((coord, distance))].push(coord);
+    }
+    for tier in tiers.iter_mut() {
+        tier.shuffle(rng);
+    }
+
+    tiers
+        .into_iter()
+        .flatten()
+        .zip(programs.iter().copied())
+        .map(|(coord, program)| Pickup { coord, program })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn place_pickups_test() {
+        let (grid, _) = super::super::parse_ascii(super::super::fixtures::MAZE).unwrap();
+        let graph = grid.as_graph();
+        let path = [(0, 1), (1, 1), (1, 0), (2, 0), (3, 0), (3, 1), (2, 1)];
+        let mut rng: rand::rngs::SmallRng = rand::SeedableRng::seed_from_u64(0);
+        let settings = ProgramGenSettings {
+            nop_slide_count: 1,
+            noclip_count: 2,
+        };
+
+        let pickups = place_pickups(&graph, &path, &[], &[], &settings.programs(), &[], &mut rng);
+        assert_eq!(pickups.len(), 3);
+        // the only dead end off of the path goes first, then the rest are on the path
+        assert_eq!(pickups[0].coord, (0, 0));
+        assert_eq!(pickups[0].program, Program::NopSlide);
+        assert!(pickups[1..]
+            .iter()
+            .all(|pickup| path.contains(&pickup.coord)));
+
+        let room = Room {
+            x: 3,
+            y: 0,
+            width: 1,
+            height: 2,
+            tag: RoomTag::Pickups,
+        };
+        let pickups = place_pickups(
+            &graph,
+            &path,
+            &[room],
+            &[],
+            &[Program::NoClip],
+            &[(3, 0)],
+            &mut rng,
+        );
+        assert_eq!(
+            pickups,
+            vec![Pickup {
+                coord: (3, 1),
+                program: Program::NoClip
+            }]
+        );
+
+        // somewhere behind a secret passage beats a dead end
+        let pickups = place_pickups(
+            &graph,
+            &path,
+            &[],
+            &[(2, 1)],
+            &[Program::NopSlide],
+            &[],
+            &mut rng,
+        );
+        assert_eq!(pickups[0].coord, (2, 1));
+    }
+}
@@ -0,0 +1,155 @@
+use super::{Coord, Direction, DirectionGrid};
+
+/// What a room is set aside for. Spawning code uses these to pick where things go.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RoomTag {
+    Empty,
+    Enemies,
+    Pickups,
+    Exit,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Room {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub tag: RoomTag,
+}
+
+impl Room {
+    pub fn contains(&self, (x, y): Coord) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = Coord> {
+        let Room {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } = *self;
+        (y..(y + height)).flat_map(move |y| (x..(x + width)).map(move |x| (x, y)))
+    }
+
+    /// Rooms that touch or overlap each other would merge into one big room.
+    fn too_close(&self, other: &Room) -> bool {
+        self.x <= other.x + other.width
+            && other.x <= self.x + self.width
+            && self.y <= other.y + other.height
+            && other.y <= self.y + self.height
+    }
+}
+
+/// Open rectangular areas carved into the maze after generation.
+/// The maze itself provides the corridors joining them.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct RoomSettings {
+    pub count: usize,
+    pub min_size: usize,
+    pub max_size: usize,
+    /// How many of the rooms are tagged `RoomTag::Enemies`.
+    pub enemy_rooms: usize,
+    /// How many of the rooms are tagged `RoomTag::Pickups`.
+    pub pickup_rooms: usize,
+    /// Whether one of the rooms is tagged `RoomTag::Exit`.
+    pub exit_room: bool,
+}
+
+pub fn carve_rooms<R: rand::Rng>(
+    grid: &mut DirectionGrid,
+    settings: RoomSettings,
+    rng: &mut R,
+) -> Vec<Room> {
+    let min_size = settings.min_size.max(2);
+    let max_size = settings.max_size.max(min_size);
+
+    let mut tags = std::iter::repeat_n(RoomTag::Exit, settings.exit_room as usize)
+        .chain(std::iter::repeat_n(RoomTag::Enemies, settings.enemy_rooms))
+        .chain(std::iter::repeat_n(RoomTag::Pickups, settings.pickup_rooms));
+
+    let mut rooms: Vec<Room> = Vec::with_capacity(settings.count);
+    let mut attempts = settings.count * 10;
+    while rooms.len() < settings.count && attempts > 0 {
+        attempts -= 1;
+
+        let width = rng.gen_range(min_size..=max_size);
+        let height = rng.gen_range(min_size..=max_size);
+        if width > grid.width || height > grid.height {
+            continue;
+        }
+        let room = Room {
+            x: rng.gen_range(0..=(grid.width - width)),
+            y: rng.gen_range(0..=(grid.height - height)),
+            width,
+            height,
+            tag: RoomTag::Empty,
+        };
+        let in_void = room.cells().any(|coord| grid.is_void(coord));
+        if !in_void && rooms.iter().all(|other| !room.too_close(other)) {
+            rooms.push(Room {
+                tag: tags.next().unwrap_or(RoomTag::Empty),
+                ..room
+            });
+        }
+    }
+
+    for room in rooms.iter() {
+        for coord in room.cells() {
+            if room.contains((coord.0 + 1, coord.1)) {
+                grid.make_open(coord, Direction::E);
+            }
+            if room.contains((coord.0, coord.1 + 1)) {
+                grid.make_open(coord, Direction::S);
+            }
+        }
+    }
+    super::apply_not_corner_bit(grid);
+
+    rooms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::MazeGenerator;
+    use rand::SeedableRng;
+
+    #[test]
+    fn carve_rooms_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut grid = crate::map::Generator::default().generate(16, 16, &mut rng);
+        let settings = RoomSettings {
+            count: 3,
+            min_size: 2,
+            max_size: 4,
+            enemy_rooms: 1,
+            pickup_rooms: 0,
+            exit_room: true,
+        };
+        let rooms = carve_rooms(&mut grid, settings, &mut rng);
+        assert_eq!(rooms.len(), 3);
+        assert_eq!(rooms[0].tag, RoomTag::Exit);
+        assert_eq!(rooms[1].tag, RoomTag::Enemies);
+        assert_eq!(rooms[2].tag, RoomTag::Empty);
+
+        for (index, room) in rooms.iter().enumerate() {
+            for other in rooms[(index + 1)..].iter() {
+                assert!(room.cells().all(|coord| !other.contains(coord)));
+            }
+
+            for coord in room.cells() {
+                let cell = grid.data[grid.coord_to_index(coord)];
+                for direction in Direction::cardinals().iter().copied() {
+                    let inside = crate::map::neighbor_coord(coord, direction)
+                        .is_ok_and(|neighbor| room.contains(neighbor));
+                    if inside {
+                        assert!(cell.contains(direction), "{:?} {:?}", coord, direction);
+                    }
+                }
+            }
+        }
+    }
+}
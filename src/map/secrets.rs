@@ -0,0 +1,257 @@
+use super::{neighbor_coord, Coord, Direction, DirectionGrid, Graph, Grid};
+use enumflags2::BitFlags;
+use rand::seq::SliceRandom;
+
+/// Open walls that are drawn as if they were shut until they're found. Kept alongside the
+/// grid as the hidden sides of each cell, with both cells of a passage marked.
+#[derive(Debug, Clone)]
+pub struct Secrets {
+    hidden: Grid<BitFlags<Direction>>,
+}
+
+impl Secrets {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            hidden: Grid::filled(width, height, BitFlags::empty()),
+        }
+    }
+
+    pub fn is_hidden(&self, from: Coord, direction: Direction) -> bool {
+        self.hidden
+            .checked_coord_to_index(from)
+            .is_some_and(|index| self.hidden.data[index].contains(direction))
+    }
+
+    /// Every cell with a secret passage out of it.
+    pub fn cells(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.hidden
+            .iter()
+            .filter(|(hidden, _)| !hidden.is_empty())
+            .map(|(_, coord)| coord)
+    }
+
+    /// Hides an open wall. Returns every cell whose tile changes, which is nothing if the
+    /// wall isn't open to begin with.
+    pub fn hide(&mut self, grid: &DirectionGrid, from: Coord, direction: Direction) -> Vec<Coord> {
+        let to = match grid.valid_move(from, direction) {
+            Some(to) if !self.is_hidden(from, direction) => to,
+            _ => return vec![],
+        };
+        self.set(from, to, direction, true);
+        around(grid, &[from, to])
+    }
+
+    /// Gives a secret passage away for good. Returns every cell whose tile changes.
+    pub fn reveal(
+        &mut self,
+        grid: &DirectionGrid,
+        from: Coord,
+        direction: Direction,
+    ) -> Vec<Coord> {
+        let to = match grid.neighbor(from, direction) {
+            Some(to) if self.is_hidden(from, direction) => to,
+            _ => return vec![],
+        };
+        self.set(from, to, direction, false);
+        around(grid, &[from, to])
+    }
+
+    fn set(&mut self, from: Coord, to: Coord, direction: Direction, hidden: bool) {
+        for (coord, direction) in [(from, direction), (to, direction.opposite())] {
+            let index = self.hidden.coord_to_index(coord);
+            if hidden {
+                self.hidden.data[index].insert(direction);
+            } else {
+                self.hidden.data[index].remove(direction);
+            }
+        }
+    }
+
+    /// How a cell looks: hidden sides are shut along with any corner they touch.
+    pub fn shown(&self, grid: &DirectionGrid, coord: Coord) -> BitFlags<Direction> {
+        let corners = [
+            (Direction::NEC, Direction::N, Direction::E),
+            (Direction::SEC, Direction::S, Direction::E),
+            (Direction::SWC, Direction::S, Direction::W),
+            (Direction::NWC, Direction::N, Direction::W),
+        ];
+        let open = |from: Coord, direction: Direction| {
+            grid.valid_move(from, direction)
+                .filter(|_| !self.is_hidden(from, direction))
+        };
+        let mut cell = grid.data[grid.coord_to_index(coord)];
+        for direction in Direction::cardinals().iter() {
+            if self.is_hidden(coord, *direction) {
+                cell.remove(*direction);
+            }
+        }
+        for (corner, a, b) in corners.iter().copied() {
+            let around = open(coord, a)
+                .and_then(|next| open(next, b))
+                .and(open(coord, b))
+                .and_then(|next| open(next, a));
+            if around.is_none() {
+                cell.remove(corner);
+            }
+        }
+        cell
+    }
+
+    /// The grid the way it's drawn, with every hidden side shut.
+    pub fn apply(&self, grid: &DirectionGrid) -> DirectionGrid {
+        Grid {
+            data: (0..grid.data.len())
+                .map(|index| self.shown(grid, grid.index_to_coord(index)))
+                .collect(),
+            width: grid.width,
+            height: grid.height,
+        }
+    }
+
+    /// Every node that can only be reached from `from` by going through a secret passage,
+    /// sorted so that choosing out of them doesn't depend on the order of a hash map.
+    pub fn behind(&self, graph: &Graph, from: Coord) -> Vec<Coord> {
+        let mut found = std::collections::HashSet::new();
+        let mut open = vec![from];
+        found.insert(from);
+        while let Some(node) = open.pop() {
+            for next in graph.neighbors(node) {
+                if !self.between(node, next) && found.insert(next) {
+                    open.push(next);
+                }
+            }
+        }
+        let mut behind = graph
+            .nodes()
+            .filter(|node| !found.contains(node))
+            .collect::<Vec<_>>();
+        behind.sort_unstable();
+        behind
+    }
+
+    fn between(&self, a: Coord, b: Coord) -> bool {
+        IntoIterator::into_iter(Direction::cardinals()).any(|direction| {
+            neighbor_coord(a, direction).ok() == Some(b) && self.is_hidden(a, direction)
+        })
+    }
+
+    /// Hides up to `count` passages leading off of `path`, each one the only way into
+    /// whatever's behind it so that there's something to find.
+    pub fn generate<R: rand::Rng>(
+        grid: &DirectionGrid,
+        graph: &Graph,
+        path: &[Coord],
+        count: usize,
+        rng: &mut R,
+    ) -> Self {
+        let mut secrets = Self::new(grid.width, grid.height);
+        let start = match path.first() {
+            Some(start) => *start,
+            None => return secrets,
+        };
+
+        let mut candidates = path
+            .iter()
+            .flat_map(|from| graph.neighbors(*from).map(move |to| (*from, to)))
+            .filter(|(_, to)| !path.contains(to))
+            .filter_map(|(from, to)| {
+                IntoIterator::into_iter(Direction::cardinals())
+                    .find(|direction| neighbor_coord(from, *direction).ok() == Some(to))
+                    .map(|direction| (from, to, direction))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(from, to, _)| (*from, *to));
+        candidates.shuffle(rng);
+
+        let mut hidden = 0;
+        for (from, to, direction) in candidates {
+            if hidden == count {
+                break;
+            }
+            secrets.hide(grid, from, direction);
+            // braided mazes can have another way round to what's behind it
+            if secrets.behind(graph, start).contains(&to) {
+                hidden += 1;
+            } else {
+                secrets.reveal(grid, from, direction);
+            }
+        }
+        secrets
+    }
+}
+
+/// Every cell a change to the walls between `cells` could show up in, corners included.
+fn around(grid: &DirectionGrid, cells: &[Coord]) -> Vec<Coord> {
+    let mut changed = vec![];
+    for (x, y) in cells.iter().copied() {
+        for nx in x.saturating_sub(1)..=x + 1 {
+            for ny in y.saturating_sub(1)..=y + 1 {
+                let coord = (nx, ny);
+                if grid.contains(coord) && !changed.contains(&coord) {
+                    changed.push(coord);
+                }
+            }
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fixtures::MAZE;
+    use super::*;
+    use rand::SeedableRng;
+
+    /// Braided on the left, where the two ways down loop back round to each other, with a
+    /// corridor on the right that's the only way to what's behind it.
+    const BRAIDED: &str = "
++-+-+-+-+
+|       |
++ + + +-+
+|   |   |
++-+-+-+-+
+";
+
+    #[test]
+    fn hide_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let mut secrets = Secrets::new(grid.width, grid.height);
+        assert!(secrets.hide(&grid, (0, 0), Direction::S).is_empty());
+        assert!(!secrets.hide(&grid, (1, 0), Direction::S).is_empty());
+        assert!(secrets.is_hidden((1, 1), Direction::N));
+        // still walkable, just not drawn that way
+        assert_eq!(grid.valid_move((1, 0), Direction::S), Some((1, 1)));
+        assert!(!secrets.shown(&grid, (1, 0)).contains(Direction::S));
+        assert!(!secrets.apply(&grid).data[grid.coord_to_index((1, 1))].contains(Direction::N));
+        assert_eq!(
+            secrets.behind(&grid.as_graph(), (3, 0)),
+            vec![(0, 1), (1, 1)]
+        );
+
+        assert!(!secrets.reveal(&grid, (1, 1), Direction::N).is_empty());
+        assert!(!secrets.is_hidden((1, 0), Direction::S));
+        assert_eq!(
+            secrets.shown(&grid, (1, 0)),
+            grid.data[grid.coord_to_index((1, 0))]
+        );
+        assert!(secrets.behind(&grid.as_graph(), (3, 0)).is_empty());
+    }
+
+    #[test]
+    fn generate_test() {
+        let (grid, _) = super::super::parse_ascii(BRAIDED).unwrap();
+        let graph = grid.as_graph();
+        let path = [(0, 0), (1, 0), (2, 0), (3, 0)];
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+
+        // either way down on the left leaves the other, so only the right one gets hidden
+        let secrets = Secrets::generate(&grid, &graph, &path, 3, &mut rng);
+        assert!(!secrets.is_hidden((0, 0), Direction::S));
+        assert!(!secrets.is_hidden((1, 0), Direction::S));
+        assert!(secrets.is_hidden((2, 0), Direction::S));
+        assert_eq!(secrets.behind(&graph, (0, 0)), vec![(2, 1), (3, 1)]);
+
+        let secrets = Secrets::generate(&grid, &graph, &path, 0, &mut rng);
+        assert_eq!(secrets.cells().count(), 0);
+    }
+}
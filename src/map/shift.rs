@@ -0,0 +1,105 @@
+use super::{bfs, bfs_path, neighbor_coord, Coord, Direction, DirectionGrid, Graph};
+use rand::seq::SliceRandom;
+
+/// A wall to knock through along with one to put up in its place.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Shift {
+    pub open: (Coord, Direction),
+    pub close: (Coord, Direction),
+}
+
+/// Finds a shut wall to open and, somewhere along the loop that opening it makes, an open
+/// one to shut again. Everything stays joined up and a perfect maze stays perfect. Walls
+/// touching `pinned` cells are left alone and `keep` gets the last word on the new graph.
+pub fn find_shift<R, F>(
+    grid: &DirectionGrid,
+    graph: &Graph,
+    pinned: &[Coord],
+    rng: &mut R,
+    keep: F,
+) -> Option<Shift>
+where
+    R: rand::Rng,
+    F: Fn(&Graph) -> bool,
+{
+    const ATTEMPTS: usize = 8;
+
+    let free = |coord: &Coord| !pinned.contains(coord) && graph.contains_node(*coord);
+    let mut walls = graph
+        .nodes()
+        .filter(free)
+        .flat_map(|from| {
+            IntoIterator::into_iter([Direction::E, Direction::S]).filter_map(move |direction| {
+                Some((from, grid.neighbor(from, direction)?, direction))
+            })
+        })
+        .filter(|(from, to, direction)| {
+            free(to) && !grid.is_void(*to) && grid.valid_move(*from, *direction).is_none()
+        })
+        .collect::<Vec<_>>();
+    walls.sort_unstable_by_key(|(from, to, _)| (*from, *to));
+    walls.shuffle(rng);
+
+    for (from, to, direction) in walls.into_iter().take(ATTEMPTS) {
+        // cells in a different part of the graph, like a walled off pocket, have no loop
+        let path = bfs_path(&bfs(graph, from), to);
+        let mut edges = path
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .filter(|(a, b)| free(a) && free(b))
+            .collect::<Vec<_>>();
+        edges.shuffle(rng);
+        for (a, b) in edges {
+            let mut shifted = graph.clone();
+            shifted.add_edge(from, to, ());
+            shifted.remove_edge(a, b);
+            if !keep(&shifted) {
+                continue;
+            }
+            let close = IntoIterator::into_iter(Direction::cardinals())
+                .find(|direction| neighbor_coord(a, *direction).ok() == Some(b))?;
+            return Some(Shift {
+                open: (from, direction),
+                close: (a, close),
+            });
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn find_shift_test() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut grid = DirectionGrid::new(8, 8, &mut rng);
+        let pinned = [(0, 0), (1, 0), (0, 1)];
+        let before = grid.clone();
+        for _ in 0..50 {
+            let graph = grid.as_graph();
+            let shift = find_shift(&grid, &graph, &pinned, &mut rng, |_| true).unwrap();
+            assert!(!grid.carve(shift.open.0, shift.open.1).is_empty());
+            assert!(!grid.seal(shift.close.0, shift.close.1).is_empty());
+
+            // still a perfect maze over every cell
+            let graph = grid.as_graph();
+            assert_eq!(graph.node_count(), 64);
+            assert_eq!(graph.edge_count(), 63);
+        }
+        for coord in pinned.iter() {
+            let index = grid.coord_to_index(*coord);
+            for direction in Direction::cardinals().iter() {
+                assert_eq!(
+                    grid.data[index].contains(*direction),
+                    before.data[index].contains(*direction)
+                );
+            }
+        }
+
+        let graph = grid.as_graph();
+        assert_eq!(find_shift(&grid, &graph, &[], &mut rng, |_| false), None);
+    }
+}
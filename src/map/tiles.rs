@@ -0,0 +1,175 @@
+use super::Direction;
+use enumflags2::BitFlags;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TileRule {
+    open: Vec<Direction>,
+    tile: String,
+}
+
+/// One theme out of `docs/images/tilesets.json`, still naming its sprites.
+/// See `TilesetRules::resolve`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TilesetRules {
+    /// Drawn, tinted black, for the void.
+    void: String,
+    /// The sprite for cells open to exactly the sides and corners listed.
+    tiles: Vec<TileRule>,
+    /// When no tile matches a cell exactly, it's cut down to each of these in turn
+    /// until one does. Cutting it down to the four sides draws corners as plain junctions.
+    #[serde(default)]
+    fallbacks: Vec<Vec<Direction>>,
+}
+
+/// Every theme in a tileset file, by name.
+pub type Tilesets = std::collections::HashMap<String, TilesetRules>;
+
+/// A theme with every cell a maze can have already matched to its sprite.
+#[derive(Clone)]
+pub struct Tileset {
+    tiles: Box<[Option<crate::UVRect>]>,
+    void: crate::UVRect,
+}
+
+fn bits(directions: &[Direction]) -> BitFlags<Direction> {
+    directions.iter().copied().collect()
+}
+
+/// Every cell a maze can produce: at least one open side, and corners only where
+/// both of the sides next to them are open too. See `apply_not_corner_bit`.
+fn possible_cells() -> impl Iterator<Item = BitFlags<Direction>> {
+    (1..=u8::MAX)
+        .filter_map(|bits| BitFlags::<Direction>::from_bits(bits).ok())
+        .filter(|cell| has_valid_corners(*cell) && !(*cell & cardinals()).is_empty())
+}
+
+fn cardinals() -> BitFlags<Direction> {
+    bits(&Direction::cardinals())
+}
+
+pub(super) fn has_valid_corners(cell: BitFlags<Direction>) -> bool {
+    let corners = [
+        (Direction::NEC, Direction::N, Direction::E),
+        (Direction::SEC, Direction::S, Direction::E),
+        (Direction::SWC, Direction::S, Direction::W),
+        (Direction::NWC, Direction::N, Direction::W),
+    ];
+    corners
+        .iter()
+        .all(|(corner, a, b)| !cell.contains(*corner) || (cell.contains(*a) && cell.contains(*b)))
+}
+
+impl TilesetRules {
+    /// Matches every possible cell to a sprite out of `sprites`. Fails if any cell is left
+    /// without a tile or a rule names a sprite that doesn't exist.
+    pub fn resolve(
+        &self,
+        name: &str,
+        sprites: &std::collections::HashMap<String, crate::UVRect>,
+    ) -> eyre::Result<Tileset> {
+        let sprite = |sprite: &str| {
+            sprites.get(sprite).copied().ok_or_else(|| {
+                eyre::Report::msg(format!(
+                    "Tileset {} uses {} which isn't in the sprite sheet.",
+                    name, sprite
+                ))
+            })
+        };
+
+        let mut exact = vec![None; 1 << 8];
+        for rule in self.tiles.iter() {
+            exact[bits(&rule.open).bits() as usize] = Some(sprite(&rule.tile)?);
+        }
+
+        let mut tiles = vec![None; 1 << 8];
+        for cell in possible_cells() {
+            let tile = std::iter::once(cell)
+                .chain(self.fallbacks.iter().map(|mask| cell & bits(mask)))
+                .find_map(|cell| exact[cell.bits() as usize]);
+            match tile {
+                Some(tile) => tiles[cell.bits() as usize] = Some(tile),
+                None => {
+                    return Err(eyre::Report::msg(format!(
+                        "Tileset {} has no tile for cells open to {:?}.",
+                        name,
+                        cell.iter().collect::<Vec<_>>()
+                    )))
+                }
+            }
+        }
+
+        Ok(Tileset {
+            tiles: tiles.into_boxed_slice(),
+            void: sprite(&self.void)?,
+        })
+    }
+}
+
+impl Tileset {
+    /// The sprite for `cell` and the color to tint it.
+    pub fn tile(&self, cell: BitFlags<Direction>) -> (crate::UVRect, [f32; 4]) {
+        // anything that isn't a possible cell can only come from a broken level so it's
+        // better to show it as a hole than to bring the whole game down
+        match self.tiles[cell.bits() as usize] {
+            Some(tile) => (tile, [1., 1., 1., 1.]),
+            None => (self.void, [0., 0., 0., 1.]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprites() -> std::collections::HashMap<String, crate::UVRect> {
+        let sheet: serde_json::Value =
+            serde_json::from_str(include_str!("../../docs/images/sprites.json")).unwrap();
+        let rect = solstice_2d::Rectangle::new(0., 0., 1., 1.);
+        sheet["frames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                let name = frame["filename"].as_str().unwrap().to_owned();
+                let uvs = crate::UVRect {
+                    positions: rect,
+                    uvs: rect,
+                };
+                (name, uvs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shipped_tilesets_test() {
+        let tilesets: Tilesets =
+            serde_json::from_str(include_str!("../../docs/images/tilesets.json")).unwrap();
+        assert!(tilesets.contains_key(crate::map::DEFAULT_TILESET));
+        let sprites = sprites();
+        for (name, rules) in tilesets.iter() {
+            let tileset = rules.resolve(name, &sprites).unwrap();
+            for cell in possible_cells() {
+                assert_eq!(tileset.tile(cell).1, [1., 1., 1., 1.]);
+            }
+            assert_eq!(tileset.tile(BitFlags::empty()).1, [0., 0., 0., 1.]);
+        }
+        assert_eq!(possible_cells().count(), 46);
+    }
+
+    #[test]
+    fn invalid_tileset_test() {
+        let sprites = sprites();
+        let rules = |json: &str| serde_json::from_str::<TilesetRules>(json).unwrap();
+
+        let missing_tile = rules(
+            r#"{ "void": "empty.png", "tiles": [{ "open": ["N"], "tile": "tiles/tile_286.png" }] }"#,
+        );
+        assert!(missing_tile.resolve("test", &sprites).is_err());
+
+        let tilesets: Tilesets =
+            serde_json::from_str(include_str!("../../docs/images/tilesets.json")).unwrap();
+        let mut missing_sprite = tilesets[crate::map::DEFAULT_TILESET].clone();
+        missing_sprite.void = "tiles/tile_342.png".to_owned();
+        assert!(missing_sprite.resolve("test", &sprites).is_err());
+    }
+}
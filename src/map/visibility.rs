@@ -0,0 +1,115 @@
+use super::{Coord, Direction, DirectionGrid, Grid};
+
+/// Every cell that can be seen from `from`: inside of a circle of `radius` cells and with
+/// a straight line of open walls between the middle of `from` and the middle of the cell.
+pub fn visible(grid: &DirectionGrid, from: Coord, radius: usize) -> Grid<bool> {
+    let mut visible = Grid::filled(grid.width, grid.height, false);
+    if !grid.contains(from) {
+        return visible;
+    }
+    let (fx, fy) = from;
+    // rounds the circle out a little so that radius 1 sees the cells it's next to
+    let limit = radius * radius + radius;
+    for x in fx.saturating_sub(radius)..=(fx + radius).min(grid.width - 1) {
+        for y in fy.saturating_sub(radius)..=(fy + radius).min(grid.height - 1) {
+            let (dx, dy) = (fx.max(x) - fx.min(x), fy.max(y) - fy.min(y));
+            if dx * dx + dy * dy <= limit && line_of_sight(grid, from, (x, y)) {
+                let index = visible.coord_to_index((x, y));
+                visible.data[index] = true;
+            }
+        }
+    }
+    visible
+}
+
+/// Walks the cells a line from the middle of `from` to the middle of `to` passes through,
+/// failing at the first wall in the way. Where the line goes exactly through the corner
+/// of a cell either way around the corner will do.
+fn line_of_sight(grid: &DirectionGrid, from: Coord, to: Coord) -> bool {
+    let (dx, dy) = (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64);
+    let (nx, ny) = (dx.abs(), dy.abs());
+    let step_x = if dx > 0 { Direction::E } else { Direction::W };
+    let step_y = if dy > 0 { Direction::S } else { Direction::N };
+
+    let (mut ix, mut iy) = (0, 0);
+    let mut coord = from;
+    while ix < nx || iy < ny {
+        // which of the next vertical or horizontal grid lines the line crosses first
+        let order = if ix == nx {
+            std::cmp::Ordering::Greater
+        } else if iy == ny {
+            std::cmp::Ordering::Less
+        } else {
+            ((1 + 2 * ix) * ny).cmp(&((1 + 2 * iy) * nx))
+        };
+        let next = match order {
+            std::cmp::Ordering::Less => {
+                ix += 1;
+                grid.valid_move(coord, step_x)
+            }
+            std::cmp::Ordering::Greater => {
+                iy += 1;
+                grid.valid_move(coord, step_y)
+            }
+            std::cmp::Ordering::Equal => {
+                ix += 1;
+                iy += 1;
+                grid.valid_move(coord, step_x)
+                    .and_then(|next| grid.valid_move(next, step_y))
+                    .or_else(|| {
+                        grid.valid_move(coord, step_y)
+                            .and_then(|next| grid.valid_move(next, step_x))
+                    })
+            }
+        };
+        match next {
+            Some(next) => coord = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "
++-+-+-+-+
+|       |
++-+-+ + +
+|       |
++-+-+-+-+
+";
+
+    #[test]
+    fn line_of_sight_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        assert!(line_of_sight(&grid, (0, 0), (3, 0)));
+        assert!(line_of_sight(&grid, (3, 0), (0, 0)));
+        assert!(line_of_sight(&grid, (2, 0), (3, 1)));
+        // the wall under the left half of the top row is in the way
+        assert!(!line_of_sight(&grid, (0, 0), (0, 1)));
+        assert!(!line_of_sight(&grid, (0, 0), (2, 1)));
+        assert!(line_of_sight(&grid, (0, 1), (0, 1)));
+    }
+
+    #[test]
+    fn visible_test() {
+        let (grid, _) = super::super::parse_ascii(MAZE).unwrap();
+        let visible = visible(&grid, (0, 0), 2);
+        assert_eq!(
+            &visible.data[..],
+            &[true, true, true, false, false, false, false, false][..]
+        );
+
+        let everything = super::visible(&grid, (2, 0), 10);
+        assert!(everything.data[..4].iter().all(|seen| *seen));
+        assert!(everything.data[6] && everything.data[7]);
+        assert!(!everything.data[4]);
+        assert!(super::visible(&grid, (9, 9), 2)
+            .data
+            .iter()
+            .all(|seen| !*seen));
+    }
+}
@@ -21,6 +21,44 @@ pub struct Programs {
 }
 
 impl Programs {
+    pub fn add(&mut self, program: crate::programs::Program) {
+        match program {
+            crate::programs::Program::NopSlide => self.nop_slide += 1,
+            crate::programs::Program::NoClip => self.clip_count += 1,
+        }
+    }
+
+    /// Gives up a program to a firewall, clips before slides. False if there wasn't one.
+    pub fn burn(&mut self) -> bool {
+        if self.clip_count > 0 {
+            self.clip_count -= 1;
+            true
+        } else if self.nop_slide > 0 {
+            self.nop_slide -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Where a step out of `start` takes the player, if anywhere. A clip only gets spent
+    /// on a step that's blocked, never on one that's open anyway.
+    pub fn step(
+        &mut self,
+        grid: &crate::map::DirectionGrid,
+        locks: &crate::map::Locks,
+        start: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> Option<crate::map::Coord> {
+        let end = crate::map::neighbor_coord(start, direction).ok()?;
+        let can_move = grid.valid_move_with(start, direction, locks).is_some();
+        if can_move || (!grid.is_void(end) && self.use_clip()) {
+            Some(end)
+        } else {
+            None
+        }
+    }
+
     fn use_clip(&mut self) -> bool {
         if self.clip_count > 0 {
             self.clip_count -= 1;
@@ -99,6 +137,20 @@ impl Player {
         }
     }
 
+    /// The cells the player has a claim on, see `crate::collision::Occupancy`.
+    pub fn occupancy(&self, map: &crate::state::Map) -> crate::collision::Occupancy {
+        match &self.state {
+            State::Stationary(state) => {
+                crate::collision::Occupancy::At(map.pixel_to_coord(state.position))
+            }
+            State::Moving(state) => crate::collision::Occupancy::Moving {
+                from: map.pixel_to_coord(state.origin),
+                to: map.pixel_to_coord(state.target),
+                progress: state.elapsed.as_secs_f32() / state.time.as_secs_f32(),
+            },
+        }
+    }
+
     pub fn is_moving(&self) -> bool {
         match &self.state {
             State::Stationary(_) => false,
@@ -111,19 +163,72 @@ impl Player {
         match &mut self.state {
             State::Stationary(state) => {
                 let start = map.pixel_to_coord(position);
-                if let Ok(end) = crate::map::neighbor_coord(start, direction) {
-                    if map.grid.valid_move(start, direction).is_some() || self.programs.use_clip() {
-                        let (x, y) = map.coord_to_mid_pixel(end);
-                        self.state = State::Moving(Moving {
-                            origin: state.position,
-                            target: (x, y),
-                            time: std::time::Duration::from_secs_f32(0.2),
-                            elapsed: Default::default(),
-                        });
-                    }
+                if let Some(end) = self.programs.step(&map.grid, &map.locks, start, direction) {
+                    let (x, y) = map.coord_to_mid_pixel(end);
+                    let time = match map.terrain(end) {
+                        crate::map::Terrain::Slow => 0.6,
+                        _ => 0.2,
+                    };
+                    self.state = State::Moving(Moving {
+                        origin: state.position,
+                        target: (x, y),
+                        time: std::time::Duration::from_secs_f32(time),
+                        elapsed: Default::default(),
+                    });
                 }
             }
             _ => {}
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Direction;
+
+    #[test]
+    fn step_test() {
+        let (grid, _) = crate::map::parse_ascii(
+            "
++-+-+
+|   |
++-+-+
+",
+        )
+        .unwrap();
+        let locks = crate::map::Locks::default();
+        let mut programs = Programs {
+            nop_slide: 0,
+            clip_count: 1,
+        };
+
+        // an open step keeps the clip for later
+        assert_eq!(
+            programs.step(&grid, &locks, (0, 0), Direction::E),
+            Some((1, 0))
+        );
+        assert_eq!(programs.clip_count, 1);
+        // off the edge of the map there's nowhere to clip into
+        assert_eq!(programs.step(&grid, &locks, (0, 0), Direction::N), None);
+        assert_eq!(programs.clip_count, 1);
+
+        // a wall takes it
+        let (grid, _) = crate::map::parse_ascii(
+            "
++-+-+
+| | |
++ + +
+|   |
++-+-+
+",
+        )
+        .unwrap();
+        assert_eq!(
+            programs.step(&grid, &locks, (0, 0), Direction::E),
+            Some((1, 0))
+        );
+        assert_eq!(programs.clip_count, 0);
+        assert_eq!(programs.step(&grid, &locks, (0, 0), Direction::E), None);
+    }
+}
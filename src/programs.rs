@@ -17,6 +17,24 @@ pub struct StateMut<'a, 'b, 'c, 'd> {
     pub map: &'a mut Map,
 }
 
+/// The programs that can be found lying around a map.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Program {
+    NopSlide,
+    NoClip,
+}
+
+impl Program {
+    /// How far the player can see while the program runs, if it lets them see further
+    /// than the level does. The slide lights up the corridors as it blasts them open.
+    pub fn sight(self) -> Option<usize> {
+        match self {
+            Program::NopSlide => Some(6),
+            Program::NoClip => None,
+        }
+    }
+}
+
 pub struct NopSlide {
     pub callback: crate::cron::ID,
 }
@@ -28,23 +46,15 @@ impl NopSlide {
 
         let step = std::time::Duration::from_secs_f32(0.1);
         let id = state.ctx.cron.every(step, move |ctx: &mut CronContext| {
-            let tiles = &ctx.shared.resources.sprites_metadata_raw;
             match &mut ctx.game_state {
                 Some(GameState::Main(main)) => {
                     let mut changed = false;
                     let dirs = std::array::IntoIter::new(Direction::cardinals());
                     for direction in dirs {
-                        use crate::map::*;
-                        let cell = neighbor_coord_mult(origin, direction, index);
+                        let cell = crate::map::neighbor_coord_mult(origin, direction, index);
                         if let Ok(cell) = cell {
-                            let [tile_width, tile_height] = main.map.inner.tile_size;
-                            main.map.inner.grid.make_open(cell, direction);
-                            let batch =
-                                create_batch(tile_width, tile_height, &main.map.inner.grid, tiles);
-                            main.map.inner.batch.clear();
-                            for quad in batch {
-                                main.map.inner.batch.push(quad);
-                            }
+                            // the slide passes over void without opening into it
+                            main.map.make_open(cell, direction);
 
                             if main.map.inner.grid.contains(cell) {
                                 changed = true
@@ -157,6 +157,7 @@ pub struct Resources {
     pub sprites_data: ImageData,
     pub noise_data: ImageData,
     pub sprites_metadata: SpriteSheet,
+    pub tilesets: crate::map::Tilesets,
     pub aesthetic_shader_src: String,
     pub menu_shader_src: String,
     pub vignette_shader_src: String,
@@ -184,6 +185,17 @@ impl Resources {
                 (entry.filename, quad)
             })
             .collect::<std::collections::HashMap<_, _>>();
+        let tilesets = self
+            .tilesets
+            .iter()
+            .map(|(name, rules)| Ok((name.clone(), rules.resolve(name, &raw)?)))
+            .collect::<eyre::Result<std::collections::HashMap<_, _>>>()?;
+        if !tilesets.contains_key(crate::map::DEFAULT_TILESET) {
+            return Err(eyre::Report::msg(format!(
+                "Missing the {} tileset.",
+                crate::map::DEFAULT_TILESET
+            )));
+        }
 
         Ok(LoadedResources {
             debug_font: gfx.add_font(self.debug_font_data.try_into()?),
@@ -193,6 +205,7 @@ impl Resources {
             sprites_metadata: Sprites::try_new(&mut raw)
                 .ok_or(eyre::Report::msg("Missing sprite definition."))?,
             sprites_metadata_raw: raw,
+            tilesets,
             shaders: Shaders {
                 aesthetic: solstice_2d::Shader::with(&self.aesthetic_shader_src, ctx)?,
                 menu: solstice_2d::Shader::with(&self.menu_shader_src, ctx)?,
@@ -295,6 +308,16 @@ pub struct LoadedResources {
     pub noise: solstice::image::Image,
     pub sprites_metadata: Sprites,
     pub sprites_metadata_raw: std::collections::HashMap<String, crate::UVRect>,
+    pub tilesets: std::collections::HashMap<String, crate::map::Tileset>,
     pub shaders: Shaders,
     pub audio: Audio,
 }
+
+impl LoadedResources {
+    pub fn tileset(&self, name: &str) -> &crate::map::Tileset {
+        self.tilesets.get(name).unwrap_or_else(|| {
+            log::warn!("No tileset named {}, using the default.", name);
+            &self.tilesets[crate::map::DEFAULT_TILESET]
+        })
+    }
+}
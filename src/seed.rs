@@ -0,0 +1,404 @@
+/// Every level of a run derives its seed from this one, so a whole run can be replayed
+/// by sharing its code, see `RunSeed::to_code`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RunSeed(pub u32);
+
+/// Crockford's base 32: no I, L, O or U so codes survive being read aloud or retyped.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LENGTH: usize = 10;
+
+impl RunSeed {
+    pub fn from_time(time: std::time::Duration) -> Self {
+        Self(time.as_millis() as u32)
+    }
+
+    /// The seed for the level at `index` into the progression. Mixed so neighbouring
+    /// levels and neighbouring runs don't end up with similar looking seeds.
+    pub fn level(self, index: usize) -> u64 {
+        splitmix64(((self.0 as u64) << 32) | index as u64)
+    }
+
+    /// A short code like `4B8ZT-K1Q3M` holding the seed and a checksum of `progression`.
+    /// The checksum catches codes shared between builds with different level settings.
+    pub fn to_code(self, progression: &crate::Progression) -> String {
+        let bits = ((self.0 as u64) << 16) | checksum(progression) as u64;
+        let mut code = String::with_capacity(CODE_LENGTH + 1);
+        for index in (0..CODE_LENGTH).rev() {
+            let digit = (bits >> (index * 5)) & 0b11111;
+            code.push(ALPHABET[digit as usize] as char);
+            if index == CODE_LENGTH / 2 {
+                code.push('-');
+            }
+        }
+        code
+    }
+
+    pub fn from_code(code: &str, progression: &crate::Progression) -> eyre::Result<Self> {
+        let mut bits = 0u64;
+        let mut length = 0;
+        for c in code.trim().chars().filter(|c| *c != '-') {
+            let c = match c.to_ascii_uppercase() {
+                'O' => '0',
+                'I' | 'L' => '1',
+                c => c,
+            };
+            let digit = ALPHABET
+                .iter()
+                .position(|a| *a as char == c)
+                .ok_or_else(|| eyre::Report::msg(format!("'{}' can't be in a seed code.", c)))?;
+            bits = (bits << 5) | digit as u64;
+            length += 1;
+        }
+        if length != CODE_LENGTH {
+            return Err(eyre::Report::msg(format!(
+                "Seed codes are {} characters long, not {}.",
+                CODE_LENGTH, length
+            )));
+        }
+
+        if (bits & 0xffff) as u16 != checksum(progression) {
+            return Err(eyre::Report::msg(
+                "Seed code is from a different version of the game.",
+            ));
+        }
+        Ok(Self((bits >> 16) as u32))
+    }
+}
+
+/// Where a level sits in a run, kept around so the HUD can show how to get back to it.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub seed: RunSeed,
+    /// How many levels into the run the current one is, whichever way it went.
+    pub level: usize,
+    pub code: String,
+}
+
+impl Run {
+    pub fn new(seed: RunSeed, progression: &crate::Progression) -> Self {
+        Self {
+            seed,
+            level: 0,
+            code: seed.to_code(progression),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        Self {
+            level: self.level + 1,
+            ..self.clone()
+        }
+    }
+
+    /// The seed for the current level unless `settings` pins one of its own.
+    pub fn level_seed(&self, settings: &crate::map::MapGenSettings) -> u64 {
+        settings.seed.unwrap_or_else(|| self.seed.level(self.level))
+    }
+}
+
+/// The seed for the enemy spawned `index`th on a level seeded with `level_seed`. Every enemy
+/// gets a stream of its own so what one of them does never changes what another does.
+pub fn enemy(level_seed: u64, index: usize) -> u64 {
+    splitmix64(level_seed ^ splitmix64(index as u64))
+}
+
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A checksum of every level's settings, folded down to 16 bits. Only what decides what a
+/// seed generates goes in, field by field, so that renaming or reordering fields or
+/// changing how the aesthetic looks leaves shared codes working.
+fn checksum(progression: &crate::Progression) -> u16 {
+    let mut checksum = Checksum::new();
+    checksum.usize(progression.levels.len());
+    for level in progression.levels.iter() {
+        match level {
+            crate::ProgressionType::Standard(progression) => {
+                checksum.u8(0);
+                checksum.progression(progression);
+            }
+            crate::ProgressionType::Authored { level, progression } => {
+                checksum.u8(1);
+                checksum.level(level);
+                checksum.progression(progression);
+            }
+            crate::ProgressionType::BadEnding => checksum.u8(2),
+        }
+    }
+    checksum.finish()
+}
+
+/// FNV-1a.
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn u8(&mut self, byte: u8) {
+        self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    fn u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes().iter() {
+            self.u8(*byte);
+        }
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u64(value.to_bits() as u64);
+    }
+
+    fn coord(&mut self, (x, y): crate::map::Coord) {
+        self.usize(x);
+        self.usize(y);
+    }
+
+    fn progression(&mut self, progression: &crate::MapProgression) {
+        self.settings(&progression.settings);
+        self.usize(progression.exits.len());
+        for exit in progression.exits.iter() {
+            self.u8(match exit.kind {
+                crate::ExitKind::Route => 0,
+                crate::ExitKind::Hidden => 1,
+            });
+            self.usize(exit.to);
+        }
+    }
+
+    fn settings(&mut self, settings: &crate::map::MapGenSettings) {
+        use crate::map::Generator;
+
+        self.usize(settings.width);
+        self.usize(settings.height);
+        match &settings.mask {
+            Some(mask) => {
+                self.u8(1);
+                self.usize(mask.width);
+                for usable in mask.data.iter() {
+                    self.u8(*usable as u8);
+                }
+            }
+            None => self.u8(0),
+        }
+        match settings.seed {
+            Some(seed) => {
+                self.u8(1);
+                self.u64(seed);
+            }
+            None => self.u8(0),
+        }
+        self.usize(settings.floors);
+
+        let generator = match settings.generator {
+            Generator::GrowingTree(weights) => {
+                for weight in [
+                    weights.random,
+                    weights.newest,
+                    weights.middle,
+                    weights.oldest,
+                ] {
+                    self.f32(weight);
+                }
+                0
+            }
+            Generator::RecursiveBacktracker => 1,
+            Generator::Kruskal => 2,
+            Generator::Prim => 3,
+            Generator::Wilson => 4,
+            Generator::Eller => 5,
+            Generator::BinaryTree => 6,
+            Generator::Sidewinder => 7,
+        };
+        self.u8(generator);
+
+        self.f32(settings.braid.dead_end_ratio);
+        self.usize(settings.braid.extra_loops);
+        let rooms = &settings.rooms;
+        for value in [
+            rooms.count,
+            rooms.min_size,
+            rooms.max_size,
+            rooms.enemy_rooms,
+            rooms.pickup_rooms,
+        ] {
+            self.usize(value);
+        }
+        self.u8(rooms.exit_room as u8);
+
+        self.usize(settings.programs.nop_slide_count);
+        self.usize(settings.programs.noclip_count);
+        let enemies = &settings.enemies;
+        for count in [
+            enemies.basic_count,
+            enemies.chaser_count,
+            enemies.patroller_count,
+            enemies.sentry_count,
+            enemies.crawler_count,
+            enemies.hunter_count,
+        ] {
+            self.usize(count);
+        }
+
+        let difficulty = &settings.difficulty;
+        for bounds in [
+            difficulty.dead_end_ratio,
+            difficulty.branching_factor,
+            difficulty.solution_ratio,
+            difficulty.river,
+            difficulty.corridor_length,
+        ] {
+            self.f32(bounds.min);
+            self.f32(bounds.max);
+        }
+        self.usize(difficulty.attempts);
+
+        self.usize(settings.doors);
+        self.usize(settings.secrets);
+        let hazards = &settings.hazards;
+        for count in [hazards.firewalls, hazards.scanners, hazards.slow] {
+            self.usize(count);
+        }
+    }
+
+    fn level(&mut self, level: &crate::map::Level) {
+        self.usize(level.width);
+        self.usize(level.height);
+        for cell in level.cells.iter() {
+            self.u8(*cell);
+        }
+        self.coord(level.start);
+        self.coord(level.exit);
+        self.usize(level.enemies.len());
+        for enemy in level.enemies.iter() {
+            self.coord(*enemy);
+        }
+        self.usize(level.pickups.len());
+        for pickup in level.pickups.iter() {
+            self.coord(pickup.coord);
+            self.u8(match pickup.program {
+                crate::programs::Program::NopSlide => 0,
+                crate::programs::Program::NoClip => 1,
+            });
+        }
+    }
+
+    fn finish(self) -> u16 {
+        let hash = self.0;
+        (hash ^ (hash >> 16) ^ (hash >> 32) ^ (hash >> 48)) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_round_trip_test() {
+        let progression = crate::Progression {
+            levels: vec![crate::ProgressionType::BadEnding],
+        };
+        for seed in [0, 1, 0xdead_beef, u32::MAX].iter().copied() {
+            let code = RunSeed(seed).to_code(&progression);
+            assert_eq!(code.len(), CODE_LENGTH + 1);
+            assert_eq!(
+                RunSeed::from_code(&code, &progression).unwrap(),
+                RunSeed(seed)
+            );
+            let sloppy = code.to_lowercase().replace('-', "").replace('0', "o");
+            assert_eq!(
+                RunSeed::from_code(&sloppy, &progression).unwrap(),
+                RunSeed(seed)
+            );
+        }
+    }
+
+    #[test]
+    fn bad_code_test() {
+        let progression = crate::Progression {
+            levels: vec![crate::ProgressionType::BadEnding],
+        };
+        let code = RunSeed(1234).to_code(&progression);
+        assert!(RunSeed::from_code(&code[1..], &progression).is_err());
+        assert!(RunSeed::from_code("UUUUU-UUUUU", &progression).is_err());
+
+        // flipping a digit of the checksum part
+        let mut tampered = code.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(RunSeed::from_code(&tampered, &progression).is_err());
+    }
+
+    #[test]
+    fn checksum_test() {
+        let settings = crate::map::MapGenSettings {
+            width: 4,
+            height: 4,
+            mask: None,
+            seed: None,
+            floors: 1,
+            generator: crate::map::Generator::default(),
+            braid: Default::default(),
+            rooms: Default::default(),
+            programs: Default::default(),
+            enemies: Default::default(),
+            difficulty: Default::default(),
+            sight: 3,
+            doors: 0,
+            secrets: 0,
+            shift_every: None,
+            hazards: Default::default(),
+            tileset: crate::map::DEFAULT_TILESET,
+            aesthetic: Default::default(),
+        };
+        let progression = |settings: &crate::map::MapGenSettings| crate::Progression {
+            levels: vec![crate::ProgressionType::Standard(Box::new(
+                crate::MapProgression {
+                    settings: settings.clone(),
+                    exits: vec![],
+                },
+            ))],
+        };
+        let before = checksum(&progression(&settings));
+
+        // how a level looks has nothing to do with what gets generated
+        let mut looks = settings.clone();
+        looks.tileset = "other";
+        looks.aesthetic.radial_scale = 2.0;
+        looks.sight = 5;
+        assert_eq!(checksum(&progression(&looks)), before);
+
+        let mut bigger = settings.clone();
+        bigger.width = 5;
+        assert_ne!(checksum(&progression(&bigger)), before);
+        let mut braided = settings;
+        braided.braid.dead_end_ratio = 0.5;
+        assert_ne!(checksum(&progression(&braided)), before);
+    }
+
+    #[test]
+    fn level_seed_test() {
+        let run = RunSeed(42);
+        assert_eq!(run.level(3), RunSeed(42).level(3));
+        assert_ne!(run.level(0), run.level(1));
+        assert_ne!(run.level(0), RunSeed(43).level(0));
+    }
+
+    #[test]
+    fn enemy_seed_test() {
+        let level = RunSeed(42).level(0);
+        assert_eq!(enemy(level, 3), enemy(level, 3));
+        assert_ne!(enemy(level, 0), enemy(level, 1));
+        assert_ne!(enemy(level, 0), enemy(RunSeed(42).level(1), 0));
+    }
+}
@@ -17,7 +17,8 @@ pub struct StateContext<'a, 'b, 'c> {
     pub input_state: &'a crate::InputState,
     pub audio_ctx: &'a mut crate::audio::AudioContext,
     pub cron: &'a mut crate::cron::Cron<crate::CronContext>,
-    pub maps: &'a crate::MapProgression,
+    pub maps: &'a crate::Progression,
+    pub seed: Option<crate::seed::RunSeed>,
     pub time: std::time::Duration,
     pub audio_sinks: &'a mut Option<crate::AudioSinks>,
 }
@@ -60,8 +61,7 @@ impl Graph {
             segments: 6,
             ..Default::default()
         };
-        let mut traversal = petgraph::visit::Bfs::new(&self.inner, (0, 0));
-        while let Some((x, y)) = traversal.next(&self.inner) {
+        for (x, y) in self.inner.nodes() {
             let color = if self.longest_path.contains(&(x, y)) {
                 [1., 1., 1., 1.]
             } else {
@@ -102,10 +102,11 @@ pub trait DrawableMap {
         tx: solstice_2d::Transform3D,
         ctx: &mut StateContext<'_, '_, '_>,
     );
+    /// Hides every cell that hasn't been seen and dims the ones that are out of `sight`.
     fn render_overlay<'a>(
         &'a self,
         player: &Player,
-        view_distance: i32,
+        sight: usize,
         ctx: &mut StateContext<'_, '_, 'a>,
     );
 }
@@ -113,85 +114,243 @@ pub trait DrawableMap {
 pub struct NavigableMap {
     pub inner: Map,
     pub graph: Graph,
+    /// Where the player starts, if it's on this floor.
+    pub start: Option<crate::map::Coord>,
+    /// Where the route through the level ends, if it's on this floor.
+    pub exit: Option<crate::map::Coord>,
+    /// Cells that end the level along with which of the level's exits they are.
+    /// Usually just `exit`, see `crate::MapProgression`.
+    pub exits: Vec<(crate::map::Coord, usize)>,
+    /// Cells that lead to another floor along with the floor they lead to.
+    pub ports: Vec<(crate::map::Coord, usize)>,
+    /// How many steps each cell is from the way off of this floor, see `NavigableMap::warmth`.
+    exit_distances: crate::map::Grid<u32>,
+    /// The most steps any reachable cell is from the way off, the coldest `warmth` gets.
+    furthest: u32,
 }
 
 impl NavigableMap {
-    pub fn with_map(map: Map) -> Self {
-        let graph = map.grid.as_graph();
-
-        fn find_dead_ends(
-            graph: &crate::map::Graph,
-        ) -> impl Iterator<Item = crate::map::Coord> + '_ {
-            let traversal = petgraph::visit::Dfs::new(graph, (0, 0));
-            petgraph::visit::Walker::iter(traversal, graph).filter_map(move |node| {
-                if graph.neighbors(node).count() == 1 {
-                    Some(node)
-                } else {
-                    None
-                }
-            })
-        }
+    fn new(
+        inner: Map,
+        graph: Graph,
+        start: Option<crate::map::Coord>,
+        exit: Option<crate::map::Coord>,
+        ports: Vec<(crate::map::Coord, usize)>,
+    ) -> Self {
+        let mut map = Self {
+            inner,
+            graph,
+            start,
+            exit,
+            exits: vec![],
+            ports,
+            exit_distances: crate::map::Grid::filled(0, 0, 0),
+            furthest: 0,
+        };
+        map.update_exit_distances();
+        map
+    }
 
-        let longest_path = {
-            let dead_ends = find_dead_ends(&graph).collect::<Vec<_>>();
-            let couples = dead_ends[..dead_ends.len() - 1]
-                .iter()
-                .copied()
-                .enumerate()
-                .flat_map(|(index, start)| {
-                    dead_ends[(index + 1)..]
-                        .iter()
-                        .copied()
-                        .map(move |end| (start, end))
-                });
-            let mut longest_path = vec![];
-            for (from, to) in couples {
-                let path = petgraph::algo::astar(&graph, from, |node| node == to, |_| 1, |_| 0);
-                if let Some((_cost, path)) = path {
-                    if path.len() > longest_path.len() {
-                        longest_path = path;
-                    }
-                }
+    fn update_exit_distances(&mut self) {
+        // the route leaves every floor it passes through from the end of its part of the path
+        let target = self
+            .exit
+            .or_else(|| self.graph.longest_path.last().copied());
+        self.exit_distances = match target {
+            Some(target) => self.inner.grid.distances(&[target]),
+            None => {
+                let ports = self
+                    .ports
+                    .iter()
+                    .map(|(coord, _)| *coord)
+                    .collect::<Vec<_>>();
+                self.inner.grid.distances(&ports)
             }
-            longest_path
         };
+        self.furthest = self
+            .exit_distances
+            .data
+            .iter()
+            .copied()
+            .filter(|distance| *distance != crate::map::UNREACHABLE)
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// Opens a wall like `Map::make_open`, keeping the graph and the way to the exit in step.
+    /// Opening walls never cuts anything off so the route stays walkable as it is.
+    pub fn make_open(
+        &mut self,
+        coord: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> bool {
+        if !self.inner.make_open(coord, direction) {
+            return false;
+        }
+        if let Some(neighbor) = self.inner.grid.neighbor(coord, direction) {
+            self.graph.inner.add_edge(coord, neighbor, ());
+        }
+        self.update_exit_distances();
+        true
+    }
+
+    /// Shuts a wall like `Map::make_closed`, keeping the graph and the way to the exit in step.
+    /// Shutting walls can cut the route so it's up to the caller to keep it walkable.
+    pub fn make_closed(
+        &mut self,
+        coord: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> bool {
+        if !self.inner.make_closed(coord, direction) {
+            return false;
+        }
+        if let Some(neighbor) = self.inner.grid.neighbor(coord, direction) {
+            self.graph.inner.remove_edge(coord, neighbor);
+        }
+        self.update_exit_distances();
+        true
+    }
+
+    /// A hot/cold hint for how close `coord` is to the exit, or to the port the route to it
+    /// leaves this floor through. 1 when it's right there, 0 at the furthest cell away.
+    pub fn warmth(&self, coord: crate::map::Coord) -> f32 {
+        let (distances, furthest) = (&self.exit_distances, self.furthest);
+        match distances.checked_coord_to_index(coord) {
+            Some(index) if distances.data[index] <= furthest && furthest > 0 => {
+                1. - distances.data[index] as f32 / furthest as f32
+            }
+            _ => 0.,
+        }
+    }
 
-        Self {
-            inner: map,
-            graph: Graph {
-                inner: graph,
+    /// A single floor with a known start and exit, like a hand-made level.
+    pub fn with_route(map: Map, start: crate::map::Coord, exit: crate::map::Coord) -> Self {
+        let inner = map.grid.as_graph();
+        let longest_path = crate::map::bfs_path(&crate::map::bfs(&inner, start), exit);
+        Self::new(
+            map,
+            Graph {
+                inner,
                 longest_path,
             },
+            Some(start),
+            Some(exit),
+            vec![],
+        )
+    }
+
+    /// Finds the route through a stack of floors joined by `ports`, see `crate::map::place_ports`.
+    /// The start and exit can end up on any floor.
+    pub fn stack(maps: Vec<Map>, ports: &[crate::map::Coord]) -> Vec<Self> {
+        let graphs = maps
+            .iter()
+            .map(|map| map.grid.as_graph())
+            .collect::<Vec<_>>();
+        let graph = crate::map::layered_graph(&graphs, ports);
+        let mut longest_path = crate::map::longest_path(&graph);
+
+        // keep the start where it is but move the exit into the furthest part of an exit room
+        let exit_cells = maps
+            .iter()
+            .enumerate()
+            .flat_map(|(floor, map)| {
+                map.rooms
+                    .iter()
+                    .filter(|room| room.tag == crate::map::RoomTag::Exit)
+                    .flat_map(move |room| room.cells().map(move |coord| (floor, coord)))
+            })
+            .collect::<Vec<_>>();
+        if let Some(start) = longest_path.first().copied() {
+            let search = crate::map::bfs(&graph, start);
+            let exit = exit_cells
+                .iter()
+                .filter_map(|cell| search.get(cell).map(|(distance, _)| (*distance, *cell)))
+                .max();
+            if let Some((_, exit)) = exit {
+                longest_path = crate::map::bfs_path(&search, exit);
+            }
         }
+
+        let start = longest_path.first().copied();
+        let exit = longest_path.last().copied();
+        maps.into_iter()
+            .zip(graphs)
+            .enumerate()
+            .map(|(floor, (map, inner))| {
+                let on_floor = |coord: Option<crate::map::FloorCoord>| {
+                    coord.filter(|(f, _)| *f == floor).map(|(_, coord)| coord)
+                };
+                let ports = ports
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(lower, coord)| {
+                        if lower == floor {
+                            Some((*coord, floor + 1))
+                        } else if lower + 1 == floor {
+                            Some((*coord, lower))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                let longest_path = longest_path
+                    .iter()
+                    .filter(|(f, _)| *f == floor)
+                    .map(|(_, coord)| *coord)
+                    .collect();
+                Self::new(
+                    map,
+                    Graph {
+                        inner,
+                        longest_path,
+                    },
+                    on_floor(start),
+                    on_floor(exit),
+                    ports,
+                )
+            })
+            .collect()
     }
 
+    /// Spawns are kept at least a couple of steps away from everything in `exclude`.
     pub fn get_enemy_spawns<'a, R: rand::Rng>(
         &'a self,
         max: usize,
-        player: &Player,
+        exclude: &'a [crate::map::Coord],
         rng: &'a mut R,
     ) -> enemy_spawn::SpawnIterator<impl FnMut() -> Option<(usize, usize)> + 'a> {
-        let player_exclusion_size = 2;
-        let player_exclusion = {
-            let (x, y) = self.inner.pixel_to_coord(player.position());
-            let x1 = x.saturating_sub(player_exclusion_size);
-            let y1 = y.saturating_sub(player_exclusion_size);
-            let x2 = (x + player_exclusion_size).max(self.inner.grid.width - 1);
-            let y2 = (y + player_exclusion_size).max(self.inner.grid.height - 1);
-            [x1, y1, x2, y2]
-        };
+        let exclusion_size = 2;
         let valid_count = self.inner.grid.width * self.inner.grid.height;
 
-        let is_excluded = move |(x, y): (usize, usize)| -> bool {
-            let [x1, y1, x2, y2] = player_exclusion;
-            x1 <= x && x < x2 && y1 <= y && y < y2
+        let distances = self.inner.grid.distances(exclude);
+        let is_excluded = move |coord: (usize, usize)| -> bool {
+            distances.data[distances.coord_to_index(coord)] <= exclusion_size
         };
 
+        // fill the rooms set aside for enemies before spreading out over the rest of the map
+        let enemy_rooms = self
+            .inner
+            .rooms
+            .iter()
+            .filter(|room| room.tag == crate::map::RoomTag::Enemies);
+        let mut room_cells = enemy_rooms
+            .clone()
+            .flat_map(|room| room.cells())
+            .collect::<Vec<_>>();
+        rand::seq::SliceRandom::shuffle(&mut room_cells[..], rng);
+        let in_enemy_room = move |coord| enemy_rooms.clone().any(|room| room.contains(coord));
+
         let mut iter = enemy_spawn::bag_random(valid_count, rng);
         enemy_spawn::SpawnIterator::new(max, move || {
+            while let Some(coord) = room_cells.pop() {
+                if !is_excluded(coord) {
+                    return Some(coord);
+                }
+            }
             while let Some(next) = iter.next() {
                 let coord = self.inner.grid.index_to_coord(next);
-                if !is_excluded(coord) {
+                let void = self.inner.grid.is_void(coord);
+                if !is_excluded(coord) && !in_enemy_room(coord) && !void {
                     return Some(coord);
                 }
             }
@@ -310,7 +469,7 @@ mod enemy_spawn {
     }
 }
 
-fn overlay(ctx: &mut StateContext<'_, '_, '_>, map: &Map, player: &Player, view_distance: i32) {
+fn overlay(ctx: &mut StateContext<'_, '_, '_>, map: &Map, player: &Player, sight: usize) {
     use solstice_2d::Draw;
 
     let [tw, th] = map.tile_size;
@@ -323,16 +482,15 @@ fn overlay(ctx: &mut StateContext<'_, '_, '_>, map: &Map, player: &Player, view_
     let u = uw / map.grid.width as f32;
     let v = uh / map.grid.height as f32;
 
-    let (px, py) = map.pixel_to_coord(player.position());
+    let visible = map.visible(player, sight);
 
     let vertices =
         map.seen
             .iter()
             .filter_map(|(seen, (x, y))| {
                 use solstice_2d::solstice::{quad_batch::Quad, viewport::Viewport};
-                if *seen {
-                    let d = (px as i32 - x as i32).abs() + (py as i32 - y as i32).abs();
-                    if d <= view_distance {
+                if *seen && !map.grid.is_void((x, y)) {
+                    if visible.data[visible.coord_to_index((x, y))] {
                         None
                     } else {
                         let (px, py) = map.coord_to_mid_pixel((x, y));
@@ -416,7 +574,22 @@ impl DrawableMap for NavigableMap {
             self.graph.draw(w, h, &mut ctx.g);
         }
 
-        if let Some(end) = self.graph.longest_path.last().copied() {
+        for (port, _) in self.ports.iter().copied() {
+            use solstice_2d::Draw;
+            let (x, y) = self.inner.coord_to_mid_pixel(port);
+            let [tw, th] = self.inner.tile_size;
+            ctx.g.image_with_color(
+                ctx.resources
+                    .sprites_metadata
+                    .exit_alpha
+                    .with_size(tw / 2., th / 2.)
+                    .center_on(x, y),
+                &ctx.resources.sprites,
+                [0.4, 0.6, 1., 1.],
+            );
+        }
+
+        for (end, _) in self.exits.iter().copied() {
             use solstice_2d::Draw;
             let (x, y) = self.inner.coord_to_mid_pixel(end);
             let [tw, th] = self.inner.tile_size;
@@ -458,10 +631,10 @@ impl DrawableMap for NavigableMap {
     fn render_overlay<'a>(
         &'a self,
         player: &Player,
-        view_distance: i32,
+        sight: usize,
         ctx: &mut StateContext<'_, '_, 'a>,
     ) {
-        overlay(ctx, &self.inner, player, view_distance);
+        overlay(ctx, &self.inner, player, sight);
     }
 }
 
@@ -508,63 +681,265 @@ impl DrawableMap for Map {
     fn render_overlay<'a>(
         &'a self,
         player: &Player,
-        view_distance: i32,
+        sight: usize,
         ctx: &mut StateContext<'_, '_, 'a>,
     ) {
-        overlay(ctx, self, player, view_distance);
+        overlay(ctx, self, player, sight);
+    }
+}
+
+/// Every tile of a map in one mesh. Unlike a `QuadBatch` any tile can be rewritten later,
+/// see `Map::flush`.
+pub struct TileBatch {
+    mesh: solstice_2d::solstice::mesh::MappedIndexedMesh<solstice_2d::Vertex2D, u32>,
+}
+
+impl TileBatch {
+    pub fn new(
+        ctx: &mut solstice_2d::solstice::Context,
+        quads: Vec<solstice_2d::solstice::quad_batch::Quad<solstice_2d::Vertex2D>>,
+    ) -> Result<Self, solstice_2d::GraphicsError> {
+        use solstice_2d::solstice::quad_batch::INDICES;
+        let indices = (0..quads.len())
+            .flat_map(|i| {
+                // past 128x128 there are more vertices than a u16 can count
+                let offset = i as u32 * 4;
+                IntoIterator::into_iter(INDICES).map(move |i| i as u32 + offset)
+            })
+            .collect();
+        let vertices = quads
+            .into_iter()
+            .flat_map(|quad| IntoIterator::into_iter(quad.vertices))
+            .collect();
+        let mesh =
+            solstice_2d::solstice::mesh::MappedIndexedMesh::with_data(ctx, vertices, indices)?;
+        Ok(Self { mesh })
+    }
+
+    pub fn set(
+        &mut self,
+        index: usize,
+        quad: solstice_2d::solstice::quad_batch::Quad<solstice_2d::Vertex2D>,
+    ) {
+        self.mesh.set_vertices(&quad.vertices, index * 4);
+    }
+
+    pub fn unmap(
+        &mut self,
+        ctx: &mut solstice_2d::solstice::Context,
+    ) -> solstice_2d::solstice::Geometry<
+        &solstice_2d::solstice::mesh::IndexedMesh<solstice_2d::Vertex2D, u32>,
+    > {
+        self.mesh.unmap(ctx);
+        self.geometry()
+    }
+
+    pub fn geometry(
+        &self,
+    ) -> solstice_2d::solstice::Geometry<
+        &solstice_2d::solstice::mesh::IndexedMesh<solstice_2d::Vertex2D, u32>,
+    > {
+        solstice_2d::solstice::Geometry {
+            mesh: self.mesh.inner(),
+            draw_range: self.mesh.draw_range(),
+            draw_mode: solstice_2d::solstice::DrawMode::Triangles,
+            instance_count: 1,
+        }
     }
 }
 
 pub struct Map {
     pub grid: crate::map::DirectionGrid,
-    pub batch: solstice_2d::solstice::quad_batch::QuadBatch<solstice_2d::Vertex2D>,
+    pub batch: TileBatch,
     pub tile_size: [f32; 2],
+    pub rooms: Vec<crate::map::Room>,
     pub seen: crate::map::Grid<bool>,
+    /// The theme the map is drawn with, see `crate::resources::LoadedResources::tileset`.
+    pub tileset: &'static str,
+    /// Cells whose tiles are out of date, see `Map::flush`.
+    dirty: Vec<crate::map::Coord>,
+    pub locks: crate::map::Locks,
+    pub secrets: crate::map::Secrets,
+    /// `grid` the way it's drawn, secret passages shut. Kept up to date rather than redone
+    /// for every `Map::visible`.
+    drawn: crate::map::DirectionGrid,
+    pub terrain: crate::map::Grid<crate::map::Terrain>,
 }
 
 impl Map {
-    pub fn with_seed(
-        width: usize,
-        height: usize,
+    /// Generates every floor of a level along with the ports joining them.
+    pub fn floors_with_seed(
+        settings: &crate::map::MapGenSettings,
         seed: u64,
         ctx: &mut StateContext,
-    ) -> Result<Self, solstice_2d::GraphicsError> {
+    ) -> Result<(Vec<Self>, Vec<crate::map::Coord>), solstice_2d::GraphicsError> {
         let mut rng: rand::rngs::SmallRng = rand::SeedableRng::seed_from_u64(seed);
-        Self::gen(width, height, ctx, &mut rng)
+        let floors = (0..settings.floors.max(1))
+            .map(|_| Self::gen(settings, ctx, &mut rng))
+            .collect::<Result<Vec<_>, _>>()?;
+        let grids = floors.iter().map(|floor| &floor.grid).collect::<Vec<_>>();
+        let ports = crate::map::place_ports(&grids, &mut rng);
+        Ok((floors, ports))
     }
 
     pub fn gen<R: rand::RngCore>(
-        width: usize,
-        height: usize,
+        settings: &crate::map::MapGenSettings,
         ctx: &mut StateContext,
         rng: &mut R,
+    ) -> Result<Self, solstice_2d::GraphicsError> {
+        let (map, rooms) = settings.generate(rng);
+        Self::with_grid(map, rooms, settings.tileset, ctx)
+    }
+
+    pub fn with_grid(
+        grid: crate::map::DirectionGrid,
+        rooms: Vec<crate::map::Room>,
+        tileset: &'static str,
+        ctx: &mut StateContext,
     ) -> Result<Self, solstice_2d::GraphicsError> {
         let tile_width = 32.;
         let tile_height = 32.;
-        let map = crate::map::Grid::new(width, height, rng);
+        let [width, height] = grid.grid_size();
+        let secrets = crate::map::Secrets::new(width, height);
         let batch = crate::map::create_batch(
             tile_width,
             tile_height,
-            &map,
-            &ctx.resources.sprites_metadata_raw,
+            &grid,
+            &secrets,
+            ctx.resources.tileset(tileset),
         );
-        let mut sp =
-            solstice_2d::solstice::quad_batch::QuadBatch::new(ctx.g.ctx_mut(), batch.len())?;
-        for quad in batch {
-            sp.push(quad);
-        }
+        let batch = TileBatch::new(ctx.g.ctx_mut(), batch)?;
         Ok(Map {
-            grid: map,
-            batch: sp,
+            batch,
             tile_size: [tile_width, tile_height],
+            rooms,
+            // there's nothing to discover in the void
             seen: crate::map::Grid {
-                data: vec![false; width * height].into_boxed_slice(),
+                data: grid.data.iter().map(|cell| cell.is_empty()).collect(),
                 width,
                 height,
             },
+            drawn: grid.clone(),
+            grid,
+            tileset,
+            dirty: vec![],
+            locks: Default::default(),
+            secrets,
+            terrain: crate::map::Grid::filled(width, height, Default::default()),
         })
     }
 
+    /// Opens a wall, see `crate::map::DirectionGrid::carve`. Returns false if it couldn't be.
+    /// A secret passage that's already there is given away instead.
+    /// The tiles catch up on the next `Map::flush`.
+    pub fn make_open(
+        &mut self,
+        coord: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> bool {
+        self.discover(coord, direction);
+        let changed = self.grid.carve(coord, direction);
+        let opened = !changed.is_empty();
+        self.changed(changed);
+        opened
+    }
+
+    /// Swaps in a new set of secret passages, redrawing whatever looks different for it.
+    pub fn set_secrets(&mut self, secrets: crate::map::Secrets) {
+        self.secrets = secrets;
+        let drawn = self.secrets.apply(&self.grid);
+        let changed = drawn
+            .data
+            .iter()
+            .zip(self.drawn.data.iter())
+            .enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(index, _)| self.grid.index_to_coord(index))
+            .collect::<Vec<_>>();
+        self.drawn = drawn;
+        self.dirty.extend(changed);
+    }
+
+    /// Catches the drawn grid up with `cells` and marks their tiles out of date.
+    fn changed(&mut self, cells: Vec<crate::map::Coord>) {
+        for coord in cells.iter() {
+            let index = self.drawn.coord_to_index(*coord);
+            self.drawn.data[index] = self.secrets.shown(&self.grid, *coord);
+        }
+        self.dirty.extend(cells);
+    }
+
+    /// Shows the secret passage out of `coord`, if there is one. Returns whether there was.
+    pub fn discover(&mut self, coord: crate::map::Coord, direction: crate::map::Direction) -> bool {
+        let changed = self.secrets.reveal(&self.grid, coord, direction);
+        let found = !changed.is_empty();
+        self.changed(changed);
+        found
+    }
+
+    /// Shuts a wall, see `crate::map::DirectionGrid::seal`. Returns false if it couldn't be.
+    pub fn make_closed(
+        &mut self,
+        coord: crate::map::Coord,
+        direction: crate::map::Direction,
+    ) -> bool {
+        let changed = self.grid.seal(coord, direction);
+        let closed = !changed.is_empty();
+        self.changed(changed);
+        closed
+    }
+
+    /// The grid the way it's drawn, with every secret passage that's still hidden shut.
+    pub fn drawn(&self) -> &crate::map::DirectionGrid {
+        &self.drawn
+    }
+
+    /// What's on the floor of `coord`. Outside of the map is clear.
+    pub fn terrain(&self, coord: crate::map::Coord) -> crate::map::Terrain {
+        self.terrain
+            .checked_coord_to_index(coord)
+            .map_or(crate::map::Terrain::Clear, |index| self.terrain.data[index])
+    }
+
+    /// Everything `player` can see from where they are. Secret passages are as good as
+    /// walls until they're found.
+    pub fn visible(&self, player: &Player, sight: usize) -> crate::map::Grid<bool> {
+        self.visible_from(self.pixel_to_coord(player.position()), sight)
+    }
+
+    /// `Map::visible` for anything in `from`, enemies included.
+    pub fn visible_from(&self, from: crate::map::Coord, sight: usize) -> crate::map::Grid<bool> {
+        crate::map::visible(&self.drawn, from, sight)
+    }
+
+    /// Marks everything `player` can see from where they are as seen.
+    pub fn reveal(&mut self, player: &Player, sight: usize) {
+        let visible = self.visible(player, sight);
+        for (seen, visible) in self.seen.data.iter_mut().zip(visible.data.iter()) {
+            *seen |= *visible;
+        }
+    }
+
+    /// Rewrites the tiles of every cell that changed since the last flush.
+    pub fn flush(&mut self, resources: &crate::resources::LoadedResources) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        let tileset = resources.tileset(self.tileset);
+        let [tile_width, tile_height] = self.tile_size;
+        for coord in self.dirty.drain(..) {
+            let quad = crate::map::tile_quad(
+                tile_width,
+                tile_height,
+                &self.grid,
+                &self.secrets,
+                coord,
+                tileset,
+            );
+            self.batch.set(self.grid.coord_to_index(coord), quad);
+        }
+    }
+
     pub fn pixel_dimensions(&self) -> [f32; 2] {
         let [tw, th] = self.tile_size;
         [tw * self.grid.width as f32, th * self.grid.height as f32]
@@ -605,6 +980,7 @@ pub enum State {
     Menu(menu::Menu),
     Main(main::Main),
     MainToMain(rotate_transition::RotateTransition<main::Main, main::Main>),
+    MainToFloor(rotate_transition::FloorTransition),
     MainToBlack(shatter_transition::ShatterTransition<main::Main, black::Black>),
     BadEnd(bad_end::BadEnd),
     MainToBadEnd(rotate_transition::RotateTransition<main::Main, bad_end::BadEnd>),
@@ -628,6 +1004,7 @@ impl State {
         let next = match self {
             State::Main(main) => main.update(dt, ctx),
             State::MainToMain(inner) => inner.update(dt, ctx),
+            State::MainToFloor(inner) => inner.update(dt, ctx),
             State::BadEnd(inner) => inner.update(dt, ctx),
             State::MainToBadEnd(inner) => inner.update(dt, ctx),
             State::Menu(_) => self,
@@ -649,6 +1026,7 @@ impl State {
             State::Menu(menu) => menu.render(ctx),
             State::Main(main) => main.render(ctx),
             State::MainToMain(inner) => inner.render(ctx),
+            State::MainToFloor(inner) => inner.render(ctx),
             State::BadEnd(inner) => inner.render(ctx),
             State::MainToBadEnd(inner) => inner.render(ctx),
             State::MainToBlack(inner) => inner.render(ctx),
@@ -665,6 +1043,7 @@ impl State {
             }
             State::Main(_) => {}
             State::MainToMain(_) => {}
+            State::MainToFloor(_) => {}
             State::BadEnd(_) => {}
             State::MainToBadEnd(_) => {}
             State::MainToBlack(_) => {}
@@ -688,6 +1067,7 @@ impl State {
                 main.handle_key_event(ctx, state, key_code);
             }
             State::MainToMain(_) => {}
+            State::MainToFloor(_) => {}
             State::BadEnd(inner) => {
                 if let Some(new_state) = inner.handle_key_event(ctx, state, key_code) {
                     *self = new_state;
@@ -1,7 +1,6 @@
 use super::{State, StateContext};
 use crate::map::{Direction, DirectionGrid, Grid};
 use enumflags2::BitFlags;
-use solstice_2d::solstice::quad_batch::QuadBatch;
 use solstice_2d::{Color, Draw};
 
 #[derive(Copy, Clone)]
@@ -61,6 +60,7 @@ pub struct BadEnd {
 
 impl BadEnd {
     pub const SCALE: f32 = 0.25;
+    pub const SIGHT: usize = 2;
     pub const AESTHETIC: crate::AestheticShader = crate::AestheticShader {
         block_threshold: 0.073,
         line_threshold: 0.23,
@@ -73,11 +73,15 @@ impl BadEnd {
     pub fn new(mut ctx: StateContext) -> Result<Self, solstice_2d::GraphicsError> {
         let grid = map_gen(WIDTH, HEIGHT);
 
-        let tiles = crate::map::create_batch(64., 64., &grid, &ctx.resources.sprites_metadata_raw);
-        let mut batch = QuadBatch::new(ctx.g.ctx_mut(), WIDTH * HEIGHT)?;
-        for tile in tiles {
-            batch.push(tile);
-        }
+        let secrets = crate::map::Secrets::new(WIDTH, HEIGHT);
+        let tiles = crate::map::create_batch(
+            64.,
+            64.,
+            &grid,
+            &secrets,
+            ctx.resources.tileset(crate::map::DEFAULT_TILESET),
+        );
+        let batch = super::TileBatch::new(ctx.g.ctx_mut(), tiles)?;
 
         let [x, y] = [
             (WIDTH as f32 / 2. - 0.5) * 64.,
@@ -85,14 +89,21 @@ impl BadEnd {
         ];
         let player = crate::player::Player::new(x, y);
         let map = super::Map {
+            drawn: grid.clone(),
             grid,
             batch,
             tile_size: [64., 64.],
+            rooms: vec![],
             seen: Grid {
                 data: vec![false; WIDTH * HEIGHT].into_boxed_slice(),
                 width: WIDTH,
                 height: HEIGHT,
             },
+            tileset: crate::map::DEFAULT_TILESET,
+            dirty: vec![],
+            locks: Default::default(),
+            secrets,
+            terrain: Grid::filled(WIDTH, HEIGHT, Default::default()),
         };
 
         let boss_show = Grid {
@@ -181,19 +192,7 @@ impl BadEnd {
 
         self.player.update(dt);
 
-        {
-            let (px, py) = self.map.pixel_to_coord(self.player.position());
-            for x in (px.saturating_sub(2))..=(px + 2) {
-                for y in (py.saturating_sub(2))..=(py + 2) {
-                    let d = (px as i32 - x as i32).abs() + (py as i32 - y as i32).abs();
-                    if d <= 2 {
-                        if let Some(index) = self.map.seen.checked_coord_to_index((x, y)) {
-                            self.map.seen.data[index] = true;
-                        }
-                    }
-                }
-            }
-        }
+        self.map.reveal(&self.player, Self::SIGHT);
         self.map.batch.unmap(ctx.g.ctx_mut());
 
         match &mut self.state {
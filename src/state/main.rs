@@ -7,64 +7,432 @@ use crate::{
 use solstice_2d::{Color, Draw};
 use ui::UIState;
 
+/// A floor the player isn't currently on. Nothing on it moves until the player comes back.
+pub struct Floor {
+    pub map: Box<NavigableMap>,
+    enemies: Vec<crate::enemy::Enemy>,
+    pickups: Vec<crate::map::Pickup>,
+}
+
 pub struct Main {
-    pub map: NavigableMap,
+    /// Boxed, like the floors, since it's most of what a `Main` weighs and `State` holds
+    /// two of them during a transition.
+    pub map: Box<NavigableMap>,
     pub player: crate::player::Player,
     pub progression: crate::MapProgression,
-    active_program: Option<crate::cron::ID>,
+    active_program: Option<(crate::programs::Program, crate::cron::ID)>,
+    /// Moves walls around on levels that ask for it, see `Main::start_shifting`.
+    shifting: Option<crate::cron::ID>,
+    /// Seeded once per level so the shifting carries on where it left off whenever
+    /// `shifting` is started again instead of playing the same shifts over.
+    shift_rng: rand::rngs::SmallRng,
     ui_state: UIState,
     enemies: Vec<crate::enemy::Enemy>,
+    pickups: Vec<crate::map::Pickup>,
+    /// Which of `floors` the player is on. That slot is always empty since
+    /// the current floor lives in `map` and `enemies` instead.
+    pub floor: usize,
+    pub floors: Vec<Option<Floor>>,
+    /// The port the player arrived through. It doesn't take them back until they step off.
+    arrived_at: Option<crate::map::Coord>,
+    run: crate::seed::Run,
 }
 
 impl Main {
-    pub fn new(
-        ctx: &mut StateContext,
-        settings: crate::MapProgression,
-    ) -> Result<Self, solstice_2d::GraphicsError> {
-        Self::with_seed(ctx, ctx.time.as_millis() as _, settings)
-    }
-
     pub fn with_seed(
         ctx: &mut StateContext,
         seed: u64,
         settings: crate::MapProgression,
+        run: crate::seed::Run,
     ) -> Result<Self, solstice_2d::GraphicsError> {
         let crate::map::MapGenSettings {
-            width,
-            height,
             enemies,
+            doors,
+            secrets,
+            hazards,
+            programs,
             ..
         } = settings.settings;
-        let map = super::Map::with_seed(width, height, seed, ctx)?;
-        let mut map = NavigableMap::with_map(map);
-        map.inner.batch.unmap(ctx.g.ctx_mut());
+        let (maps, ports) = super::Map::floors_with_seed(&settings.settings, seed, ctx)?;
+        let maps = NavigableMap::stack(maps, &ports);
 
+        let floor = maps.iter().position(|map| map.start.is_some()).unwrap_or(0);
+        let start = maps[floor].start.unwrap_or((0, 0));
         let player = {
-            // let start = map.graph.longest_path.last().copied().unwrap();
-            let start = map.graph.longest_path[0];
-            let (x, y) = map.inner.coord_to_mid_pixel(start);
+            let (x, y) = maps[floor].inner.coord_to_mid_pixel(start);
             crate::player::Player::new(x, y)
         };
 
+        // spread the enemies out over every floor, away from the start and the ports
         let mut rng: rand::rngs::SmallRng = rand::SeedableRng::seed_from_u64(seed);
-        let enemies = map
-            .get_enemy_spawns(enemies.basic_count, &player, &mut rng)
-            .map(|coord| {
-                let (x, y) = map.inner.coord_to_mid_pixel(coord);
-                crate::enemy::Enemy::new_basic(x, y)
+        let floor_count = maps.len();
+        let programs = programs.programs();
+        let kinds = enemies.kinds();
+        let mut floors = maps
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut map)| {
+                let blocked = if map.exit.is_some() {
+                    Self::place_exits(&mut map, &settings.exits, &mut rng)
+                } else {
+                    vec![]
+                };
+                let secrets = crate::map::Secrets::generate(
+                    &map.inner.grid,
+                    &map.graph.inner,
+                    &map.graph.longest_path,
+                    secrets,
+                    &mut rng,
+                );
+                map.inner.set_secrets(secrets);
+                let hidden = match map.graph.longest_path.first() {
+                    Some(start) => map.inner.secrets.behind(&map.graph.inner, *start),
+                    None => vec![],
+                };
+                // the route through each floor starts wherever the player arrives on it
+                map.inner.locks = crate::map::Locks::generate(
+                    &map.graph.inner,
+                    &map.graph.longest_path,
+                    doors,
+                    &hidden,
+                    &mut rng,
+                );
+                // enemies are dealt out like the programs are
+                let floor_kinds = kinds
+                    .iter()
+                    .enumerate()
+                    .skip(index)
+                    .step_by(floor_count)
+                    .map(|(spawn_index, kind)| (spawn_index, *kind))
+                    .collect::<Vec<_>>();
+                let exclude = map
+                    .start
+                    .iter()
+                    .copied()
+                    .chain(map.ports.iter().map(|(coord, _)| *coord))
+                    .chain(blocked.iter().copied())
+                    .collect::<Vec<_>>();
+                let spawns = map
+                    .get_enemy_spawns(floor_kinds.len(), &exclude, &mut rng)
+                    .collect::<Vec<_>>();
+
+                // programs are dealt out over the floors like cards
+                let floor_programs = programs
+                    .iter()
+                    .skip(index)
+                    .step_by(floor_count)
+                    .copied()
+                    .collect::<Vec<_>>();
+                let taken = exclude
+                    .iter()
+                    .copied()
+                    .chain(map.exit)
+                    .chain(map.inner.locks.keys.iter().map(|key| key.coord))
+                    .collect::<Vec<_>>();
+                map.inner.terrain = crate::map::place_hazards(
+                    &map.graph.inner,
+                    &map.graph.longest_path,
+                    hazards,
+                    &taken,
+                    map.inner.grid.grid_size(),
+                    &mut rng,
+                );
+                // firewalls sit in the same dead ends that pickups like best
+                let firewalls = map
+                    .inner
+                    .terrain
+                    .iter()
+                    .filter(|(terrain, _)| **terrain == crate::map::Terrain::Firewall)
+                    .map(|(_, coord)| coord);
+                let taken = taken.iter().copied().chain(firewalls).collect::<Vec<_>>();
+                let pickups = crate::map::place_pickups(
+                    &map.graph.inner,
+                    &map.graph.longest_path,
+                    &map.inner.rooms,
+                    &hidden,
+                    &floor_programs,
+                    &taken,
+                    &mut rng,
+                );
+                log::debug!(
+                    "Floor {} of level {} with seed {}:\n{}",
+                    index,
+                    run.level,
+                    seed,
+                    map.inner.grid.to_ascii_with(&crate::map::Overlay {
+                        player: map.start,
+                        enemies: &spawns,
+                        exit: map.exit,
+                        path: &map.graph.longest_path,
+                        ..Default::default()
+                    })
+                );
+                log::debug!(
+                    "{:?}, enemies are {:?} steps from the solution",
+                    crate::map::Metrics::new(&map.graph.inner, &map.graph.longest_path),
+                    crate::map::path_distances(&map.graph.inner, &map.graph.longest_path, &spawns)
+                );
+                let enemies = spawns
+                    .iter()
+                    .zip(floor_kinds)
+                    .map(|(coord, (spawn_index, kind))| {
+                        let enemy_seed = crate::seed::enemy(seed, spawn_index);
+                        kind.spawn(&map, *coord, enemy_seed, &mut rng)
+                    })
+                    .collect::<Vec<_>>();
+                Some(Floor {
+                    map: Box::new(map),
+                    enemies,
+                    pickups,
+                })
             })
             .collect::<Vec<_>>();
+        for floor in floors.iter_mut().flatten() {
+            floor.map.inner.flush(ctx.resources);
+            floor.map.inner.batch.unmap(ctx.g.ctx_mut());
+        }
+        let Floor {
+            map,
+            enemies,
+            pickups,
+        } = floors[floor].take().unwrap();
 
         Ok(Self {
             map,
             player,
             active_program: None,
+            shifting: None,
+            shift_rng: rand::SeedableRng::seed_from_u64(seed),
+            progression: settings,
+            ui_state: UIState::Closed,
+            enemies,
+            pickups,
+            floor,
+            floors,
+            arrived_at: Some(start),
+            run,
+        })
+    }
+
+    /// Plays a hand-made level instead of generating one. Only the aesthetic, tileset and
+    /// exit of `settings` are used.
+    pub fn with_level(
+        ctx: &mut StateContext,
+        level: &crate::map::Level,
+        settings: crate::MapProgression,
+        run: crate::seed::Run,
+    ) -> eyre::Result<Self> {
+        let grid = level.grid()?;
+        let map = super::Map::with_grid(grid, vec![], settings.settings.tileset, ctx)?;
+        let mut map = NavigableMap::with_route(map, level.start, level.exit);
+        let seed = run.level_seed(&settings.settings);
+        let mut rng: rand::rngs::SmallRng = rand::SeedableRng::seed_from_u64(seed);
+        Self::place_exits(&mut map, &settings.exits, &mut rng);
+        map.inner.flush(ctx.resources);
+        map.inner.batch.unmap(ctx.g.ctx_mut());
+
+        let player = {
+            let (x, y) = map.inner.coord_to_mid_pixel(level.start);
+            crate::player::Player::new(x, y)
+        };
+        let enemies = level
+            .enemies
+            .iter()
+            .enumerate()
+            .map(|(index, coord)| {
+                let (x, y) = map.inner.coord_to_mid_pixel(*coord);
+                crate::enemy::Enemy::new_basic(x, y, crate::seed::enemy(seed, index))
+            })
+            .collect();
+
+        Ok(Self {
+            map: Box::new(map),
+            player,
+            active_program: None,
+            shifting: None,
+            shift_rng: rand::SeedableRng::seed_from_u64(seed),
             progression: settings,
             ui_state: UIState::Closed,
             enemies,
+            pickups: level.pickups.clone(),
+            floor: 0,
+            floors: vec![None],
+            arrived_at: Some(level.start),
+            run,
+        })
+    }
+
+    /// Finds a cell on `map` for each of `exits`, which should only happen on the floor the
+    /// route ends on. Returns every cell that's been used up, pockets included.
+    fn place_exits<R: rand::Rng>(
+        map: &mut NavigableMap,
+        exits: &[crate::Exit],
+        rng: &mut R,
+    ) -> Vec<crate::map::Coord> {
+        let from = map
+            .start
+            .or_else(|| map.graph.longest_path.first().copied());
+        let mut avoid = map
+            .start
+            .iter()
+            .copied()
+            .chain(map.ports.iter().map(|(coord, _)| *coord))
+            .chain(map.graph.longest_path.iter().copied())
+            .collect::<Vec<_>>();
+        let mut blocked = vec![];
+        let mut route = map.exit;
+        for (index, exit) in exits.iter().enumerate() {
+            let coord = match exit.kind {
+                crate::ExitKind::Route => route.take().or_else(|| {
+                    from.and_then(|from| crate::map::far_dead_end(&map.graph.inner, from, &avoid))
+                }),
+                crate::ExitKind::Hidden => crate::map::find_pocket(&map.graph.inner, &avoid, rng)
+                    .filter(|pocket| map.make_closed(pocket.wall.0, pocket.wall.1))
+                    .map(|pocket| {
+                        avoid.push(pocket.wall.0);
+                        blocked.push(pocket.wall.0);
+                        pocket.exit
+                    }),
+            };
+            match coord {
+                Some(coord) => {
+                    avoid.push(coord);
+                    blocked.push(coord);
+                    map.exits.push((coord, index));
+                }
+                None => log::warn!("Nowhere to put the exit to level {}.", exit.to),
+            }
+        }
+        blocked
+    }
+
+    /// Starts whichever level `progression` points to, seeded from `run` unless the level
+    /// pins its own seed. The bad ending isn't played through `Main` so there's nothing to
+    /// start for it.
+    pub fn from_progression(
+        ctx: &mut StateContext,
+        run: crate::seed::Run,
+        progression: &ProgressionType,
+    ) -> Option<eyre::Result<Self>> {
+        match progression {
+            ProgressionType::Standard(settings) => {
+                let seed = run.level_seed(&settings.settings);
+                Some(
+                    Self::with_seed(ctx, seed, (**settings).clone(), run)
+                        .map_err(eyre::Report::new),
+                )
+            }
+            ProgressionType::Authored { level, progression } => {
+                Some(Self::with_level(ctx, level, (**progression).clone(), run))
+            }
+            ProgressionType::BadEnding => None,
+        }
+    }
+
+    /// Moves a wall every `every` for as long as this level is being played.
+    fn start_shifting(
+        &self,
+        every: std::time::Duration,
+        cron: &mut crate::cron::Cron<crate::CronContext>,
+    ) -> crate::cron::ID {
+        let level = self.run.level;
+        cron.every(every, move |ctx: &mut crate::CronContext| {
+            match &mut ctx.game_state {
+                Some(State::Main(main)) if main.run.level == level => {
+                    main.shift_walls();
+                    crate::cron::ControlFlow::Continue
+                }
+                _ => crate::cron::ControlFlow::Stop,
+            }
         })
     }
 
+    /// Knocks a wall through and puts another one up without cutting anything off that
+    /// could be reached before. Nothing moves right next to the player or an enemy so
+    /// that nobody gets caught in a wall, and doors and secret passages stay put.
+    fn shift_walls(&mut self) {
+        let map = &self.map.inner;
+        let occupied = std::iter::once(self.player.position()).chain(
+            self.enemies
+                .iter()
+                .map(|enemy| (enemy.position[0], enemy.position[1])),
+        );
+        let mut pinned = vec![];
+        for position in occupied {
+            let coord = map.pixel_to_coord(position);
+            pinned.push(coord);
+            pinned.extend(
+                IntoIterator::into_iter(crate::map::Direction::cardinals())
+                    .filter_map(|direction| map.grid.neighbor(coord, direction)),
+            );
+        }
+        for door in map.locks.doors.iter() {
+            pinned.push(door.cell);
+            pinned.extend(map.grid.neighbor(door.cell, door.direction));
+        }
+        pinned.extend(map.secrets.cells());
+
+        let player = map.pixel_to_coord(self.player.position());
+        let graph = &self.map.graph.inner;
+        let targets = self
+            .map
+            .exits
+            .iter()
+            .chain(self.map.ports.iter())
+            .map(|(coord, _)| *coord)
+            .filter(|coord| map.locks.solvable(graph, player, *coord))
+            .collect::<Vec<_>>();
+        let shift =
+            crate::map::find_shift(&map.grid, graph, &pinned, &mut self.shift_rng, |graph| {
+                targets
+                    .iter()
+                    .all(|target| map.locks.solvable(graph, player, *target))
+            });
+        if let Some(crate::map::Shift { open, close }) = shift {
+            self.map.make_open(open.0, open.1);
+            self.map.make_closed(close.0, close.1);
+        }
+    }
+
+    /// Game over, back to black.
+    fn shatter(self) -> State {
+        State::MainToBlack(super::shatter_transition::ShatterTransition::new(
+            self,
+            super::black::Black::new(std::time::Duration::from_secs_f32(1.)),
+            std::time::Duration::from_secs_f32(1.5),
+        ))
+    }
+
+    /// Swaps the current floor out for another one.
+    fn change_floor(mut self, to: usize) -> State {
+        let next = self.floors.get_mut(to).and_then(Option::take);
+        if let Some(mut next) = next {
+            std::mem::swap(&mut self.map, &mut next.map);
+            std::mem::swap(&mut self.enemies, &mut next.enemies);
+            std::mem::swap(&mut self.pickups, &mut next.pickups);
+            self.floors[self.floor] = Some(next);
+            let from = std::mem::replace(&mut self.floor, to);
+            State::MainToFloor(super::rotate_transition::FloorTransition {
+                main: self,
+                from,
+                elapsed: Default::default(),
+                time: std::time::Duration::from_secs_f32(1.5),
+            })
+        } else {
+            log::error!("No floor {} to move to.", to);
+            State::Main(self)
+        }
+    }
+
+    /// How far the player can see right now, see `crate::map::visible`.
+    pub fn sight(&self) -> usize {
+        let program = self
+            .active_program
+            .and_then(|(program, _)| program.sight())
+            .unwrap_or(0);
+        self.progression.settings.sight.max(program)
+    }
+
     pub fn handle_key_event(
         &mut self,
         mut ctx: StateContext,
@@ -84,6 +452,13 @@ impl Main {
     pub fn update(mut self, dt: std::time::Duration, mut ctx: StateContext) -> State {
         self.ui_state.update(dt);
 
+        if let Some(every) = self.progression.settings.shift_every {
+            // anything that isn't this level stops the last one, a floor change included
+            if !self.shifting.is_some_and(|id| ctx.cron.contains(id)) {
+                self.shifting = Some(self.start_shifting(every, ctx.cron));
+            }
+        }
+
         for enemy in self.enemies.iter_mut() {
             let mut prog_ctx = crate::programs::StateMut {
                 ctx: &mut ctx,
@@ -94,15 +469,11 @@ impl Main {
             if enemy.collides_with(&self.player, &self.map.inner) {
                 let laugh = ctx.sinks().agent_smith_laugh.clone();
                 ctx.audio_ctx.play(&laugh);
-                return State::MainToBlack(super::shatter_transition::ShatterTransition::new(
-                    self,
-                    super::black::Black::new(std::time::Duration::from_secs_f32(1.)),
-                    std::time::Duration::from_secs_f32(1.5),
-                ));
+                return self.shatter();
             }
         }
 
-        if let Some(active_program) = self.active_program {
+        if let Some((_, active_program)) = self.active_program {
             if !ctx.cron.contains(active_program) {
                 self.active_program = None;
             }
@@ -123,49 +494,83 @@ impl Main {
 
         if !self.ui_state.is_open() {
             if let Some(direction) = direction {
+                let from = self.map.inner.pixel_to_coord(self.player.position());
+                let was_moving = self.player.is_moving();
                 self.player.try_grid_move(direction, &self.map.inner);
+                if !was_moving && self.player.is_moving() {
+                    // walking into a fake wall gives it away
+                    self.map.inner.discover(from, direction);
+                    let to = crate::map::neighbor_coord(from, direction).unwrap_or(from);
+                    match self.map.inner.terrain(to) {
+                        crate::map::Terrain::Firewall => {
+                            if !self.player.programs.burn() {
+                                return self.shatter();
+                            }
+                        }
+                        crate::map::Terrain::Scanner => {
+                            for enemy in self.enemies.iter_mut() {
+                                enemy.alert(to);
+                            }
+                        }
+                        crate::map::Terrain::Slow | crate::map::Terrain::Clear => {}
+                    }
+                }
             }
         }
 
         self.player.update(dt);
 
-        {
-            let (px, py) = self.map.inner.pixel_to_coord(self.player.position());
-            for x in (px.saturating_sub(2))..=(px + 2) {
-                for y in (py.saturating_sub(2))..=(py + 2) {
-                    let d = (px as i32 - x as i32).abs() + (py as i32 - y as i32).abs();
-                    if d <= 2 {
-                        if let Some(index) = self.map.inner.seen.checked_coord_to_index((x, y)) {
-                            self.map.inner.seen.data[index] = true;
-                        }
-                    }
+        self.map.inner.reveal(&self.player, self.sight());
+
+        if !self.player.is_moving() {
+            let grid_pos = self.map.inner.pixel_to_coord(self.player.position());
+            let player = &mut self.player;
+            self.pickups.retain(|pickup| {
+                let collected = pickup.coord == grid_pos;
+                if collected {
+                    player.programs.add(pickup.program);
                 }
+                !collected
+            });
+            if let Some(key) = self.map.inner.locks.take_key(grid_pos) {
+                log::debug!("Picked up key {}.", key);
             }
         }
 
         {
             // player is at exit
             let grid_pos = self.map.inner.pixel_to_coord(self.player.position());
-            if let Some(target) = self.map.graph.longest_path.last().copied() {
-                if !self.player.is_moving() && grid_pos == target {
-                    let seed = ctx.time.as_millis() as u64;
-                    if let Some(progression) = &self.progression.exit {
+            let exit = self.map.exits.iter().find(|(coord, _)| *coord == grid_pos);
+            if let Some((_, index)) = exit.copied() {
+                if !self.player.is_moving() {
+                    let maps = ctx.maps;
+                    if let Some(progression) = self
+                        .progression
+                        .exits
+                        .get(index)
+                        .and_then(|exit| maps.levels.get(exit.to))
+                    {
                         let sound = ctx.sinks().level_finish.clone();
                         ctx.audio_ctx.play(&sound);
                         match progression {
-                            ProgressionType::Standard(settings) => {
-                                // let to = Self::with_seed(&mut ctx, seed, self.progression.clone());
-                                let to = Self::with_seed(&mut ctx, seed, (**settings).clone());
-                                if let Ok(mut to) = to {
-                                    to.player.programs = self.player.programs;
-                                    return State::MainToMain(
-                                        super::rotate_transition::RotateTransition {
-                                            from: self,
-                                            to,
-                                            time: std::time::Duration::from_secs_f32(3.),
-                                            elapsed: Default::default(),
-                                        },
-                                    );
+                            ProgressionType::Standard(_) | ProgressionType::Authored { .. } => {
+                                match Self::from_progression(&mut ctx, self.run.next(), progression)
+                                {
+                                    Some(Ok(mut to)) => {
+                                        to.player.programs = self.player.programs;
+                                        return State::MainToMain(
+                                            super::rotate_transition::RotateTransition {
+                                                from: self,
+                                                to,
+                                                time: std::time::Duration::from_secs_f32(3.),
+                                                elapsed: Default::default(),
+                                            },
+                                        );
+                                    }
+                                    Some(Err(err)) => {
+                                        log::error!("Error loading the next level: {}", err);
+                                    }
+                                    None => {}
                                 }
                             }
                             ProgressionType::BadEnding => {
@@ -190,13 +595,86 @@ impl Main {
             }
         }
 
+        {
+            // player is on a port
+            let grid_pos = self.map.inner.pixel_to_coord(self.player.position());
+            if self.arrived_at != Some(grid_pos) {
+                self.arrived_at = None;
+            }
+            let port = self.map.ports.iter().find(|(port, _)| *port == grid_pos);
+            if let Some((port, to)) = port.copied() {
+                if !self.player.is_moving() && self.arrived_at.is_none() {
+                    self.arrived_at = Some(port);
+                    self.map.inner.flush(ctx.resources);
+                    self.map.inner.batch.unmap(ctx.g.ctx_mut());
+                    return self.change_floor(to);
+                }
+            }
+        }
+
         self.ui_state.set_open(ctx.input_state.ctrl);
 
+        self.map.inner.flush(ctx.resources);
         self.map.inner.batch.unmap(ctx.g.ctx_mut());
 
         State::Main(self)
     }
 
+    fn render_hazards(&self, ctx: &mut StateContext) {
+        let map = &self.map.inner;
+        let [tw, th] = map.tile_size;
+        let pulse = (ctx.time.as_secs_f32() * 4.).sin() * 0.5 + 0.5;
+        for (terrain, coord) in map.terrain.iter() {
+            let color = match terrain {
+                crate::map::Terrain::Clear => continue,
+                crate::map::Terrain::Firewall => [1., 0.3, 0.1, 0.4 + pulse * 0.3],
+                // scanners sweep on and off
+                crate::map::Terrain::Scanner => [0.2, 1., 0.5, pulse * 0.5],
+                crate::map::Terrain::Slow => [0.4, 0.4, 1., 0.3],
+            };
+            let (x, y) = map.coord_to_mid_pixel(coord);
+            let (w, h) = (tw * 0.9, th * 0.9);
+            let tile = solstice_2d::Rectangle::new(x - w / 2., y - h / 2., w, h);
+            ctx.g.draw_with_color(tile, color);
+        }
+    }
+
+    fn render_locks(&self, ctx: &mut StateContext) {
+        const KEY_COLORS: [[f32; 4]; 4] = [
+            [1., 0.3, 0.3, 1.],
+            [0.3, 0.6, 1., 1.],
+            [1., 0.9, 0.2, 1.],
+            [0.8, 0.4, 1., 1.],
+        ];
+        let color = |key: usize| KEY_COLORS[key % KEY_COLORS.len()];
+        let map = &self.map.inner;
+        let [tw, th] = map.tile_size;
+
+        for door in map.locks.doors.iter() {
+            let (x, y) = map.coord_to_mid_pixel(door.cell);
+            let (dx, dy) = door.direction.into_dir();
+            let (x, y) = (x + dx as f32 * tw / 2., y + dy as f32 * th / 2.);
+            // a bar lying along the wall the door sits in
+            let (w, h) = if dx == 0 {
+                (tw * 0.8, th * 0.2)
+            } else {
+                (tw * 0.2, th * 0.8)
+            };
+            let bar = solstice_2d::Rectangle::new(x - w / 2., y - h / 2., w, h);
+            ctx.g.draw_with_color(bar, color(door.key));
+        }
+
+        for key in map.locks.keys.iter() {
+            let (x, y) = map.coord_to_mid_pixel(key.coord);
+            let size = tw * 0.3;
+            let transform = solstice_2d::Transform2D::translation(x, y)
+                * solstice_2d::Transform2D::rotation(solstice_2d::Rad(ctx.time.as_secs_f32()));
+            let square = solstice_2d::Rectangle::new(-size / 2., -size / 2., size, size);
+            ctx.g
+                .draw_with_color_and_transform(square, color(key.key), transform);
+        }
+    }
+
     pub fn render_into_canvas<'a>(&'a mut self, ctx: &mut StateContext<'_, '_, 'a>) {
         let (w, h) = ctx.aesthetic_canvas.dimensions();
         let mut camera = super::Camera::new(w, h);
@@ -211,8 +689,39 @@ impl Main {
             ctx.g.clear(BLACK);
 
             self.map.render(&self.player, ctx);
+            self.render_hazards(ctx);
+
+            for pickup in self.pickups.iter() {
+                let (x, y) = self.map.inner.coord_to_mid_pixel(pickup.coord);
+                let [tw, _] = self.map.inner.tile_size;
+                let color = match pickup.program {
+                    crate::programs::Program::NopSlide => [0.4, 1., 0.6, 1.],
+                    crate::programs::Program::NoClip => [1., 0.8, 0.3, 1.],
+                };
+                let circle = solstice_2d::Circle {
+                    radius: tw * 0.2,
+                    segments: 6,
+                    ..Default::default()
+                };
+                let transform = solstice_2d::Transform2D::translation(x, y)
+                    * solstice_2d::Transform2D::rotation(solstice_2d::Rad(ctx.time.as_secs_f32()));
+                ctx.g
+                    .draw_with_color_and_transform(circle, color, transform);
+            }
+
+            self.render_locks(ctx);
 
+            // enemies around a corner stay hidden even on cells that have been seen
+            let visible = self.map.inner.visible(&self.player, self.sight());
             for enemy in self.enemies.iter_mut() {
+                let [x, y] = enemy.position;
+                let coord = self.map.inner.pixel_to_coord((x, y));
+                let in_sight = visible
+                    .checked_coord_to_index(coord)
+                    .is_some_and(|index| visible.data[index]);
+                if !in_sight {
+                    continue;
+                }
                 let mut ctx = crate::programs::State {
                     ctx,
                     player: &self.player,
@@ -221,7 +730,7 @@ impl Main {
                 enemy.render(&mut ctx);
             }
 
-            self.map.render_overlay(&self.player, 2, ctx);
+            self.map.render_overlay(&self.player, self.sight(), ctx);
 
             ctx.g.set_camera(solstice_2d::Transform2D::default());
         }
@@ -261,11 +770,16 @@ impl Main {
 
         g.set_camera(solstice_2d::Transform2D::default());
 
+        let warmth = self
+            .map
+            .warmth(self.map.inner.pixel_to_coord(self.player.position()));
         self.ui_state.render(
             g,
             ctx.resources,
             &self.player,
             ctx.audio_ctx.global_volume(),
+            warmth,
+            &self.run.code,
         );
     }
 
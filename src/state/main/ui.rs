@@ -50,7 +50,7 @@ impl UIState {
         state: crate::ElementState,
         key_code: crate::VirtualKeyCode,
         prog_state: crate::programs::StateMut,
-    ) -> Option<crate::cron::ID> {
+    ) -> Option<(crate::programs::Program, crate::cron::ID)> {
         if let UIState::Open(open) = self {
             match state {
                 ElementState::Pressed => match key_code {
@@ -64,7 +64,7 @@ impl UIState {
                     crate::VirtualKeyCode::D => match open.selected {
                         0 => {
                             let r = crate::programs::NopSlide::new(prog_state);
-                            return Some(r.callback);
+                            return Some((crate::programs::Program::NopSlide, r.callback));
                         }
                         1 => {
                             crate::programs::NoClip::new(prog_state);
@@ -153,6 +153,8 @@ impl UIState {
         resources: &crate::resources::LoadedResources,
         player: &crate::player::Player,
         volume: f32,
+        warmth: f32,
+        seed_code: &str,
     ) {
         use solstice_2d::Rectangle;
         const BG: solstice_2d::Color = solstice_2d::Color::new(0.2, 0.2, 0.2, 1.);
@@ -236,6 +238,25 @@ impl UIState {
                     g.print(text, font_id, SCALE, text_bounds(index));
                 }
                 g.print("EOF", font_id, SCALE, text_bounds(count));
+                let signal = if warmth > 0.8 {
+                    "HOT"
+                } else if warmth > 0.4 {
+                    "WARM"
+                } else {
+                    "COLD"
+                };
+                g.print(
+                    format!("exit: {}", signal),
+                    font_id,
+                    SCALE,
+                    text_bounds(count + 2),
+                );
+                g.print(
+                    format!("run {}", seed_code),
+                    font_id,
+                    SCALE,
+                    text_bounds(count + 3),
+                );
                 g.set_color([1., 1., 0., 1.]);
                 g.print(">", font_id, SCALE, {
                     let mut b = text_bounds(state.selected);
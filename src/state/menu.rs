@@ -213,9 +213,15 @@ impl Menu {
                         ctx.audio_ctx.play(&music);
                         self.music = Some(music);
                     }
-                    let settings = ctx.maps.clone();
-                    let main = super::main::Main::new(&mut ctx, settings).ok()?;
-                    Some(State::Main(main))
+                    let maps = ctx.maps;
+                    let seed = ctx
+                        .seed
+                        .unwrap_or_else(|| crate::seed::RunSeed::from_time(ctx.time));
+                    let run = crate::seed::Run::new(seed, maps);
+                    match super::main::Main::from_progression(&mut ctx, run, maps.start()) {
+                        Some(main) => main.ok().map(State::Main),
+                        None => super::bad_end::BadEnd::new(ctx).ok().map(State::BadEnd),
+                    }
                 }
                 _ => None,
             }
@@ -6,6 +6,7 @@ struct RenderState<'a> {
     map: &'a mut Map,
     player: &'a Player,
     aesthetic: crate::AestheticShader,
+    sight: usize,
     camera: super::Camera,
 }
 
@@ -71,13 +72,19 @@ fn render<'a>(mut ctx: StateContext<'a, '_, 'a>, ratio: f32, states: [RenderStat
     );
 
     use solstice_2d::Rad;
-    for (index, RenderState { map, player, .. }) in std::array::IntoIter::new(states).enumerate() {
+    for (
+        index,
+        RenderState {
+            map, player, sight, ..
+        },
+    ) in std::array::IntoIter::new(states).enumerate()
+    {
         ctx.g.set_canvas(Some(ctx.canvas.clone()));
         ctx.g.clear(BLACK);
 
         use super::DrawableMap;
         map.render(player, &mut ctx);
-        map.render_overlay(player, 2, &mut ctx);
+        map.render_overlay(player, sight, &mut ctx);
         ctx.g.set_camera(solstice_2d::Transform2D::default());
 
         let g = &mut ctx.g;
@@ -194,12 +201,14 @@ impl RotateTransition<Main, Main> {
     pub fn render(&mut self, ctx: StateContext) {
         let ratio = self.elapsed.as_secs_f32() / self.time.as_secs_f32();
         let (w, h) = ctx.aesthetic_canvas.dimensions();
+        let (from_sight, to_sight) = (self.from.sight(), self.to.sight());
         let mut from_camera = super::Camera::new(w, h);
         from_camera.for_map_with_scale(&self.from.map.inner, &self.from.player, 1.);
         let from = RenderState {
             map: &mut self.from.map.inner,
             player: &self.from.player,
             aesthetic: self.from.progression.settings.aesthetic,
+            sight: from_sight,
             camera: from_camera,
         };
         let mut to_camera = super::Camera::new(w, h);
@@ -208,6 +217,7 @@ impl RotateTransition<Main, Main> {
             map: &mut self.to.map.inner,
             player: &self.to.player,
             aesthetic: self.to.progression.settings.aesthetic,
+            sight: to_sight,
             camera: to_camera,
         };
         render(ctx, ratio, [from, to]);
@@ -227,12 +237,14 @@ impl RotateTransition<Main, BadEnd> {
     pub fn render(&mut self, ctx: StateContext) {
         let ratio = self.elapsed.as_secs_f32() / self.time.as_secs_f32();
         let (w, h) = ctx.aesthetic_canvas.dimensions();
+        let from_sight = self.from.sight();
         let mut from_camera = super::Camera::new(w, h);
         from_camera.for_map_with_scale(&self.from.map.inner, &self.from.player, 1.);
         let from = RenderState {
             map: &mut self.from.map.inner,
             player: &self.from.player,
             aesthetic: self.from.progression.settings.aesthetic,
+            sight: from_sight,
             camera: from_camera,
         };
         let mut to_camera = super::Camera::new(w, h);
@@ -246,6 +258,57 @@ impl RotateTransition<Main, BadEnd> {
             map: &mut self.to.map,
             player: &self.to.player,
             aesthetic: BadEnd::AESTHETIC,
+            sight: BadEnd::SIGHT,
+            camera: to_camera,
+        };
+        render(ctx, ratio, [from, to]);
+    }
+}
+
+/// Moving between floors of the same level. `main` is already on the new floor.
+pub struct FloorTransition {
+    pub main: Main,
+    pub from: usize,
+    pub elapsed: std::time::Duration,
+    pub time: std::time::Duration,
+}
+
+impl FloorTransition {
+    pub fn update(mut self, dt: std::time::Duration, _ctx: StateContext) -> State {
+        self.elapsed += dt;
+        if self.elapsed >= self.time {
+            State::Main(self.main)
+        } else {
+            State::MainToFloor(self)
+        }
+    }
+
+    pub fn render(&mut self, ctx: StateContext) {
+        let ratio = self.elapsed.as_secs_f32() / self.time.as_secs_f32();
+        let main = &mut self.main;
+        let sight = main.sight();
+        let from = match main.floors.get_mut(self.from) {
+            Some(Some(floor)) => &mut floor.map.inner,
+            _ => return,
+        };
+        let aesthetic = main.progression.settings.aesthetic;
+        let (w, h) = ctx.aesthetic_canvas.dimensions();
+        let mut from_camera = super::Camera::new(w, h);
+        from_camera.for_map_with_scale(from, &main.player, 1.);
+        let from = RenderState {
+            map: from,
+            player: &main.player,
+            aesthetic,
+            sight,
+            camera: from_camera,
+        };
+        let mut to_camera = super::Camera::new(w, h);
+        to_camera.for_map_with_scale(&main.map.inner, &main.player, 1.);
+        let to = RenderState {
+            map: &mut main.map.inner,
+            player: &main.player,
+            aesthetic,
+            sight,
             camera: to_camera,
         };
         render(ctx, ratio, [from, to]);
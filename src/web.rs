@@ -5,6 +5,9 @@ use wasm_bindgen::prelude::*;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+/// Used when the page doesn't hand over a tileset file, see `ResourcesWrapper::set_tilesets`.
+const BUILT_IN_TILESETS: &str = include_str!("../docs/images/tilesets.json");
+
 fn into_js_value<E: std::fmt::Display>(err: E) -> JsValue {
     JsValue::from_str(&format!("{}", err))
 }
@@ -50,6 +53,7 @@ pub struct ResourcesWrapper {
     sprites_data: Option<ImageData>,
     noise_data: Option<ImageData>,
     sprites_metadata: Option<SpriteSheet>,
+    tilesets: Option<crate::map::Tilesets>,
     aesthetic_shader_src: Option<String>,
     menu_shader_src: Option<String>,
     vignette_shader_src: Option<String>,
@@ -73,6 +77,7 @@ impl ResourcesWrapper {
             sprites_data: None,
             noise_data: None,
             sprites_metadata: None,
+            tilesets: None,
             aesthetic_shader_src: None,
             menu_shader_src: None,
             vignette_shader_src: None,
@@ -105,6 +110,12 @@ impl ResourcesWrapper {
         Ok(())
     }
 
+    pub fn set_tilesets(&mut self, data: Vec<u8>) -> Result<(), JsValue> {
+        let tilesets = serde_json::from_slice(&data).map_err(into_js_value)?;
+        self.tilesets = Some(tilesets);
+        Ok(())
+    }
+
     pub fn set_noise(&mut self, image: web_sys::HtmlImageElement) {
         Self::set_image(&mut self.noise_data, image)
     }
@@ -214,6 +225,11 @@ impl Wrapper {
             sprites_metadata: resources
                 .sprites_metadata
                 .ok_or(JsValue::from_str("missing sprites metadata"))?,
+            // a page that doesn't fetch the tileset file gets the one the game was built with
+            tilesets: match resources.tilesets {
+                Some(tilesets) => tilesets,
+                None => serde_json::from_str(BUILT_IN_TILESETS).map_err(into_js_value)?,
+            },
             aesthetic_shader_src: resources
                 .aesthetic_shader_src
                 .ok_or(JsValue::from_str("missing aesthetic shader source"))?,
@@ -251,6 +267,10 @@ impl Wrapper {
         self.inner.update(duration_from_f64(time_ms));
     }
 
+    pub fn set_seed_code(&mut self, code: String) -> Result<(), JsValue> {
+        self.inner.set_seed_code(&code).map_err(into_js_value)
+    }
+
     pub fn handle_resize(&mut self) {
         let width = self.canvas.width();
         let height = self.canvas.height();